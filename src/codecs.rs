@@ -0,0 +1,90 @@
+pub mod astc;
+pub mod etc;
+pub mod ktx;
+
+use etc::EtcFormat;
+use image::RgbaImage;
+use rayon::prelude::*;
+
+/// A block-compressed pixel format.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BlockFormat {
+    /// ETC1 or ETC2 data.
+    Etc(EtcFormat),
+    /// ASTC LDR data with a block footprint of the given width and height.
+    Astc(u32, u32),
+}
+
+impl BlockFormat {
+    /// Returns the number of bytes of data for an image of the given size.
+    pub fn data_size(self, width: u32, height: u32) -> usize {
+        match self {
+            Self::Etc(format) => etc::data_size(format, width, height),
+            Self::Astc(block_width, block_height) => {
+                astc::data_size(block_width, block_height, width, height)
+            }
+        }
+    }
+
+    /// Decodes data of this format into an image.
+    ///
+    /// `data` must be at least [`data_size`] bytes long.
+    ///
+    /// [`data_size`]: #method.data_size
+    pub fn decode(self, data: &[u8], width: u32, height: u32) -> RgbaImage {
+        match self {
+            Self::Etc(format) => etc::decode(data, format, width, height),
+            Self::Astc(block_width, block_height) => {
+                astc::decode(data, block_width, block_height, width, height)
+            }
+        }
+    }
+}
+
+/// Decodes an image made of blocks of `block_width` x `block_height` texels.
+///
+/// Every row of blocks is decoded in parallel, straight into the image buffer.
+/// `decode_block` is called with the index of a block and fills a buffer of
+/// its texels, stored row by row. Texels outside the image are discarded.
+pub(crate) fn decode_blocks<F>(
+    width: u32,
+    height: u32,
+    block_width: u32,
+    block_height: u32,
+    decode_block: F,
+) -> RgbaImage
+where
+    F: Fn(usize, &mut [[u8; 4]]) + Sync,
+{
+    let (width, height) = (width as usize, height as usize);
+    let (block_width, block_height) = (block_width as usize, block_height as usize);
+    let blocks_x = width.div_ceil(block_width);
+    let row_size = width * 4;
+
+    let mut buf = vec![0; row_size * height];
+    if row_size > 0 {
+        buf.par_chunks_mut(row_size * block_height)
+            .enumerate()
+            .for_each(|(by, rows)| {
+                let mut texels = vec![[0; 4]; block_width * block_height];
+
+                for bx in 0..blocks_x {
+                    decode_block(by * blocks_x + bx, &mut texels);
+
+                    let x = bx * block_width;
+                    let columns = block_width.min(width - x);
+                    for (y, row) in rows.chunks_exact_mut(row_size).enumerate() {
+                        let texels = &texels[y * block_width..y * block_width + columns];
+                        for (pixel, texel) in row[x * 4..(x + columns) * 4]
+                            .chunks_exact_mut(4)
+                            .zip(texels)
+                        {
+                            pixel.copy_from_slice(texel);
+                        }
+                    }
+                }
+            });
+    }
+
+    RgbaImage::from_raw(width as u32, height as u32, buf).expect("buffer fits the image")
+}
@@ -0,0 +1,726 @@
+use super::decode_blocks;
+use image::RgbaImage;
+
+/// Color of texels in blocks that are invalid or use HDR features.
+const ERROR_COLOR: [u8; 4] = [255, 0, 255, 255];
+
+/// Integer sequence encodings of the quantization levels, as
+/// `(trits, quints, bits)`.
+///
+/// Weights use the first 12 levels, color endpoints use the levels from `4`.
+const LEVELS: [(bool, bool, u32); 21] = [
+    (false, false, 1),
+    (true, false, 0),
+    (false, false, 2),
+    (false, true, 0),
+    (true, false, 1),
+    (false, false, 3),
+    (false, true, 1),
+    (true, false, 2),
+    (false, false, 4),
+    (false, true, 2),
+    (true, false, 3),
+    (false, false, 5),
+    (false, true, 3),
+    (true, false, 4),
+    (false, false, 6),
+    (false, true, 4),
+    (true, false, 5),
+    (false, false, 7),
+    (false, true, 5),
+    (true, false, 6),
+    (false, false, 8),
+];
+
+/// Decodes ASTC LDR data with a block footprint of `block_width` by
+/// `block_height` texels into an image.
+///
+/// Every block is 16 bytes and blocks are stored row by row. `data` must be
+/// at least [`data_size`] bytes long. Blocks using HDR endpoint modes or
+/// reserved encodings are decoded to magenta, like hardware decoders do.
+///
+/// [`data_size`]: ./fn.data_size.html
+pub fn decode(
+    data: &[u8],
+    block_width: u32,
+    block_height: u32,
+    width: u32,
+    height: u32,
+) -> RgbaImage {
+    decode_blocks(width, height, block_width, block_height, |index, texels| {
+        let mut bytes = [0; 16];
+        bytes.copy_from_slice(&data[index * 16..(index + 1) * 16]);
+
+        texels.copy_from_slice(&decode_block(
+            u128::from_le_bytes(bytes),
+            block_width as usize,
+            block_height as usize,
+        ));
+    })
+}
+
+/// Returns the number of bytes of ASTC data for an image of the given size.
+pub fn data_size(block_width: u32, block_height: u32, width: u32, height: u32) -> usize {
+    let blocks_x = width.div_ceil(block_width);
+    let blocks_y = height.div_ceil(block_height);
+
+    blocks_x as usize * blocks_y as usize * 16
+}
+
+/// Extracts `len` bits of `block`, starting at bit `start`.
+///
+/// Bits at or after `end` read as zero.
+fn bits(block: u128, start: u32, len: u32, end: u32) -> u32 {
+    if start >= end || len == 0 {
+        return 0;
+    }
+
+    let len = len.min(end - start);
+    ((block >> start) & ((1 << len) - 1)) as u32
+}
+
+/// Repeats the `from` low bits of `value` to fill `to` bits.
+fn replicate(value: u32, from: u32, to: u32) -> u32 {
+    let mut out = 0;
+    let mut shift = to as i32 - from as i32;
+
+    while shift > -(from as i32) {
+        if shift >= 0 {
+            out |= value << shift;
+        } else {
+            out |= value >> -shift;
+        }
+        shift -= from as i32;
+    }
+
+    out & ((1 << to) - 1)
+}
+
+/// Returns the number of bits used by `count` integers of a quantization
+/// level.
+fn sequence_bits(count: u32, level: usize) -> u32 {
+    let (trits, quints, n) = LEVELS[level];
+
+    let extra = if trits {
+        (8 * count).div_ceil(5)
+    } else if quints {
+        (7 * count).div_ceil(3)
+    } else {
+        0
+    };
+
+    n * count + extra
+}
+
+/// Unpacks five trits from their packed 8-bit representation.
+fn unpack_trits(t: u32) -> [u32; 5] {
+    let bit = |value: u32, i: u32| (value >> i) & 1;
+
+    let (c, t3, t4) = if (t >> 2) & 7 == 7 {
+        ((((t >> 5) & 7) << 2) | (t & 3), 2, 2)
+    } else if (t >> 5) & 3 == 3 {
+        (t & 0x1F, bit(t, 7), 2)
+    } else {
+        (t & 0x1F, (t >> 5) & 3, bit(t, 7))
+    };
+
+    let (t0, t1, t2) = if c & 3 == 3 {
+        (
+            (bit(c, 3) << 1) | (bit(c, 2) & !bit(c, 3) & 1),
+            bit(c, 4),
+            2,
+        )
+    } else if (c >> 2) & 3 == 3 {
+        (c & 3, 2, 2)
+    } else {
+        (
+            (bit(c, 1) << 1) | (bit(c, 0) & !bit(c, 1) & 1),
+            (c >> 2) & 3,
+            bit(c, 4),
+        )
+    };
+
+    [t0, t1, t2, t3, t4]
+}
+
+/// Unpacks three quints from their packed 7-bit representation.
+fn unpack_quints(q: u32) -> [u32; 3] {
+    let bit = |value: u32, i: u32| (value >> i) & 1;
+
+    if (q >> 1) & 3 == 3 && (q >> 5) & 3 == 0 {
+        let q2 =
+            (bit(q, 0) << 2) | ((bit(q, 4) & !bit(q, 0) & 1) << 1) | (bit(q, 3) & !bit(q, 0) & 1);
+
+        return [4, 4, q2];
+    }
+
+    let (c, q2) = if (q >> 1) & 3 == 3 {
+        (
+            (((q >> 3) & 3) << 3) | ((!(q >> 5) & 3) << 1) | bit(q, 0),
+            4,
+        )
+    } else {
+        (q & 0x1F, (q >> 5) & 3)
+    };
+
+    if c & 7 == 5 {
+        [(c >> 3) & 3, 4, q2]
+    } else {
+        [c & 7, (c >> 3) & 3, q2]
+    }
+}
+
+/// Decodes `count` integers of a quantization level, starting at bit `start`.
+///
+/// Every integer is returned as its trit or quint followed by its low bits.
+fn decode_sequence(block: u128, start: u32, count: u32, level: usize) -> Vec<u32> {
+    let (trits, quints, n) = LEVELS[level];
+    let end = start + sequence_bits(count, level);
+
+    let mut out = Vec::with_capacity(count as usize + 4);
+    let mut pos = start;
+    let mut read = |len: u32| {
+        let value = bits(block, pos, len, end);
+        pos += len;
+        value
+    };
+
+    while out.len() < count as usize {
+        if trits {
+            let mut m = [0; 5];
+            let mut t = 0;
+
+            m[0] = read(n);
+            t |= read(2);
+            m[1] = read(n);
+            t |= read(2) << 2;
+            m[2] = read(n);
+            t |= read(1) << 4;
+            m[3] = read(n);
+            t |= read(2) << 5;
+            m[4] = read(n);
+            t |= read(1) << 7;
+
+            for (trit, low) in unpack_trits(t).iter().zip(m.iter()) {
+                out.push((trit << n) | low);
+            }
+        } else if quints {
+            let mut m = [0; 3];
+            let mut q = 0;
+
+            m[0] = read(n);
+            q |= read(3);
+            m[1] = read(n);
+            q |= read(2) << 3;
+            m[2] = read(n);
+            q |= read(2) << 5;
+
+            for (quint, low) in unpack_quints(q).iter().zip(m.iter()) {
+                out.push((quint << n) | low);
+            }
+        } else {
+            out.push(read(n));
+        }
+    }
+
+    out.truncate(count as usize);
+    out
+}
+
+/// Unquantizes a color endpoint value to 8 bits.
+fn unquantize_color(value: u32, level: usize) -> u32 {
+    let (trits, quints, n) = LEVELS[level];
+
+    if !trits && !quints {
+        return replicate(value, n, 8);
+    }
+
+    let d = value >> n;
+    let a = if value & 1 != 0 { 0x1FF } else { 0 };
+    let x = (value & ((1 << n) - 1)) >> 1;
+
+    let (b, c) = match (trits, n) {
+        (true, 1) => (0, 204),
+        (true, 2) => (x * 0x116, 93),
+        (true, 3) => ((x << 7) | (x << 2) | x, 44),
+        (true, 4) => ((x << 6) | x, 22),
+        (true, 5) => ((x << 5) | (x >> 2), 11),
+        (true, _) => ((x << 4) | (x >> 4), 5),
+        (false, 1) => (0, 113),
+        (false, 2) => (x * 0x10C, 54),
+        (false, 3) => ((x << 7) | (x << 1) | (x >> 1), 26),
+        (false, 4) => ((x << 6) | (x >> 1), 13),
+        (false, _) => ((x << 5) | (x >> 3), 6),
+    };
+
+    let t = (d * c + b) ^ a;
+    (a & 0x80) | (t >> 2)
+}
+
+/// Unquantizes a weight value to the range `0..=64`.
+fn unquantize_weight(value: u32, level: usize) -> u32 {
+    let (trits, quints, n) = LEVELS[level];
+
+    let weight = if !trits && !quints {
+        replicate(value, n, 6)
+    } else if n == 0 {
+        return if trits { value * 32 } else { value * 16 };
+    } else {
+        let d = value >> n;
+        let a = if value & 1 != 0 { 0x7F } else { 0 };
+        let x = (value & ((1 << n) - 1)) >> 1;
+
+        let (b, c) = match (trits, n) {
+            (true, 1) => (0, 50),
+            (true, 2) => (x * 0x45, 23),
+            (true, _) => ((x << 5) | x, 11),
+            (false, 1) => (0, 28),
+            (false, _) => (x * 0x42, 13),
+        };
+
+        let t = (d * c + b) ^ a;
+        (a & 0x20) | (t >> 2)
+    };
+
+    if weight > 32 {
+        weight + 1
+    } else {
+        weight
+    }
+}
+
+/// Weight grid parameters of a block.
+struct BlockMode {
+    width: usize,
+    height: usize,
+    dual_plane: bool,
+    level: usize,
+}
+
+/// Decodes the 11-bit block mode of a non void-extent block.
+fn decode_block_mode(mode: u32) -> Option<BlockMode> {
+    let a = (mode >> 5) & 3;
+    let mut high_precision = (mode >> 9) & 1 != 0;
+    let mut dual_plane = (mode >> 10) & 1 != 0;
+
+    let (range, width, height) = if mode & 3 != 0 {
+        let range = ((mode >> 4) & 1) | ((mode & 3) << 1);
+        let b = (mode >> 7) & 3;
+
+        let (width, height) = match (mode >> 2) & 3 {
+            0 => (b + 4, a + 2),
+            1 => (b + 8, a + 2),
+            2 => (a + 2, b + 8),
+            _ if mode & 0x100 != 0 => ((b & 1) + 2, a + 2),
+            _ => (a + 2, (b & 1) + 6),
+        };
+
+        (range, width, height)
+    } else {
+        let range = ((mode >> 4) & 1) | (((mode >> 2) & 3) << 1);
+        if (mode >> 2) & 3 == 0 {
+            return None;
+        }
+
+        let (width, height) = match (mode >> 7) & 3 {
+            0 => (12, a + 2),
+            1 => (a + 2, 12),
+            2 => {
+                high_precision = false;
+                dual_plane = false;
+                (a + 6, ((mode >> 9) & 3) + 6)
+            }
+            _ => match a {
+                0 => (6, 10),
+                1 => (10, 6),
+                _ => return None,
+            },
+        };
+
+        (range, width, height)
+    };
+
+    Some(BlockMode {
+        width: width as usize,
+        height: height as usize,
+        dual_plane,
+        level: (range - 2) as usize + if high_precision { 6 } else { 0 },
+    })
+}
+
+/// Transfers the top bit of `a` to `b`, making `a` a signed 6-bit offset.
+fn bit_transfer_signed(a: i32, b: i32) -> (i32, i32) {
+    let b = (b >> 1) | (a & 0x80);
+    let a = (a >> 1) & 0x3F;
+
+    (if a & 0x20 != 0 { a - 0x40 } else { a }, b)
+}
+
+/// Moves a color towards blue, undoing the blue contraction of the encoder.
+fn blue_contract(r: i32, g: i32, b: i32, a: i32) -> [i32; 4] {
+    [(r + b) >> 1, (g + b) >> 1, b, a]
+}
+
+/// Decodes the endpoints of a color endpoint mode from unquantized values.
+///
+/// Returns `None` for HDR modes.
+fn decode_endpoints(mode: u32, v: &[i32]) -> Option<([i32; 4], [i32; 4])> {
+    let endpoints = match mode {
+        0 => ([v[0], v[0], v[0], 255], [v[1], v[1], v[1], 255]),
+        1 => {
+            let l0 = (v[0] >> 2) | (v[1] & 0xC0);
+            let l1 = (l0 + (v[1] & 0x3F)).min(255);
+            ([l0, l0, l0, 255], [l1, l1, l1, 255])
+        }
+        4 => ([v[0], v[0], v[0], v[2]], [v[1], v[1], v[1], v[3]]),
+        5 => {
+            let (l1, l0) = bit_transfer_signed(v[1], v[0]);
+            let (a1, a0) = bit_transfer_signed(v[3], v[2]);
+            let l1 = l0 + l1;
+            ([l0, l0, l0, a0], [l1, l1, l1, a0 + a1])
+        }
+        6 => (
+            [
+                (v[0] * v[3]) >> 8,
+                (v[1] * v[3]) >> 8,
+                (v[2] * v[3]) >> 8,
+                255,
+            ],
+            [v[0], v[1], v[2], 255],
+        ),
+        8 | 12 => {
+            let (a0, a1) = if mode == 12 { (v[6], v[7]) } else { (255, 255) };
+
+            if v[1] + v[3] + v[5] >= v[0] + v[2] + v[4] {
+                ([v[0], v[2], v[4], a0], [v[1], v[3], v[5], a1])
+            } else {
+                (
+                    blue_contract(v[1], v[3], v[5], a1),
+                    blue_contract(v[0], v[2], v[4], a0),
+                )
+            }
+        }
+        9 | 13 => {
+            let (r1, r0) = bit_transfer_signed(v[1], v[0]);
+            let (g1, g0) = bit_transfer_signed(v[3], v[2]);
+            let (b1, b0) = bit_transfer_signed(v[5], v[4]);
+            let (a1, a0) = if mode == 13 {
+                bit_transfer_signed(v[7], v[6])
+            } else {
+                (0, 255)
+            };
+
+            if r1 + g1 + b1 >= 0 {
+                ([r0, g0, b0, a0], [r0 + r1, g0 + g1, b0 + b1, a0 + a1])
+            } else {
+                (
+                    blue_contract(r0 + r1, g0 + g1, b0 + b1, a0 + a1),
+                    blue_contract(r0, g0, b0, a0),
+                )
+            }
+        }
+        10 => (
+            [
+                (v[0] * v[3]) >> 8,
+                (v[1] * v[3]) >> 8,
+                (v[2] * v[3]) >> 8,
+                v[4],
+            ],
+            [v[0], v[1], v[2], v[5]],
+        ),
+        _ => return None,
+    };
+
+    let clamp = |color: [i32; 4]| {
+        let mut out = [0; 4];
+        for (o, c) in out.iter_mut().zip(color.iter()) {
+            *o = (*c).clamp(0, 255);
+        }
+        out
+    };
+
+    Some((clamp(endpoints.0), clamp(endpoints.1)))
+}
+
+/// Mixes the bits of a partition seed.
+fn hash52(mut p: u32) -> u32 {
+    p ^= p >> 15;
+    p = p.wrapping_sub(p << 17);
+    p = p.wrapping_add(p << 7);
+    p = p.wrapping_add(p << 4);
+    p ^= p >> 5;
+    p = p.wrapping_add(p << 16);
+    p ^= p >> 7;
+    p ^= p >> 3;
+    p ^= p << 6;
+    p ^= p >> 17;
+    p
+}
+
+/// Returns the partition of the texel at `x`, `y`.
+fn select_partition(seed: u32, x: u32, y: u32, count: u32, small_block: bool) -> usize {
+    let (x, y) = if small_block {
+        (x << 1, y << 1)
+    } else {
+        (x, y)
+    };
+
+    let seed = seed + (count - 1) * 1024;
+    let rnum = hash52(seed);
+
+    let mut s = [
+        rnum & 0xF,
+        (rnum >> 4) & 0xF,
+        (rnum >> 8) & 0xF,
+        (rnum >> 12) & 0xF,
+        (rnum >> 16) & 0xF,
+        (rnum >> 20) & 0xF,
+        (rnum >> 24) & 0xF,
+        (rnum >> 28) & 0xF,
+    ];
+    for value in s.iter_mut() {
+        *value *= *value;
+    }
+
+    let (sh1, sh2) = if seed & 1 != 0 {
+        (
+            if seed & 2 != 0 { 4 } else { 5 },
+            if count == 3 { 6 } else { 5 },
+        )
+    } else {
+        (
+            if count == 3 { 6 } else { 5 },
+            if seed & 2 != 0 { 4 } else { 5 },
+        )
+    };
+    for (i, value) in s.iter_mut().enumerate() {
+        *value >>= if i % 2 == 0 { sh1 } else { sh2 };
+    }
+
+    // The z terms of 3D blocks are always zero here.
+    let a = (s[0] * x + s[1] * y + (rnum >> 14)) & 0x3F;
+    let b = (s[2] * x + s[3] * y + (rnum >> 10)) & 0x3F;
+    let c = if count < 3 {
+        0
+    } else {
+        (s[4] * x + s[5] * y + (rnum >> 6)) & 0x3F
+    };
+    let d = if count < 4 {
+        0
+    } else {
+        (s[6] * x + s[7] * y + (rnum >> 2)) & 0x3F
+    };
+
+    if a >= b && a >= c && a >= d {
+        0
+    } else if b >= c && b >= d {
+        1
+    } else if c >= d {
+        2
+    } else {
+        3
+    }
+}
+
+/// Interpolates the weight grid of a plane to the texel at `s`, `t`.
+fn infill_weight(
+    weights: &[u32],
+    mode: &BlockMode,
+    block: (usize, usize),
+    s: usize,
+    t: usize,
+) -> u32 {
+    let ds = (1024 + block.0 / 2) / (block.0 - 1).max(1);
+    let dt = (1024 + block.1 / 2) / (block.1 - 1).max(1);
+
+    let gs = (ds * s * (mode.width - 1) + 32) >> 6;
+    let gt = (dt * t * (mode.height - 1) + 32) >> 6;
+    let (js, fs) = (gs >> 4, (gs & 0xF) as u32);
+    let (jt, ft) = (gt >> 4, (gt & 0xF) as u32);
+
+    let w11 = (fs * ft + 8) >> 4;
+    let w10 = ft - w11;
+    let w01 = fs - w11;
+    let w00 = 16 - fs - ft + w11;
+
+    let weight = |x: usize, y: usize| {
+        if x < mode.width && y < mode.height {
+            weights[y * mode.width + x]
+        } else {
+            0
+        }
+    };
+
+    (weight(js, jt) * w00
+        + weight(js + 1, jt) * w01
+        + weight(js, jt + 1) * w10
+        + weight(js + 1, jt + 1) * w11
+        + 8)
+        >> 4
+}
+
+/// Decodes a single block into its texels, row by row.
+fn decode_block(block: u128, block_width: usize, block_height: usize) -> Vec<[u8; 4]> {
+    let texel_count = block_width * block_height;
+    let error = vec![ERROR_COLOR; texel_count];
+    let all = 128;
+
+    let mode = bits(block, 0, 11, all);
+
+    // Void-extent blocks have a single color.
+    if mode & 0x1FF == 0x1FC {
+        if mode & 0x200 != 0 {
+            return error;
+        }
+
+        let mut color = [0; 4];
+        for (i, c) in color.iter_mut().enumerate() {
+            *c = (bits(block, 64 + 16 * i as u32, 16, all) >> 8) as u8;
+        }
+
+        return vec![color; texel_count];
+    }
+
+    let mode = match decode_block_mode(mode) {
+        Some(v) => v,
+        None => return error,
+    };
+    let partition_count = bits(block, 11, 2, all) + 1;
+    let plane_count = if mode.dual_plane { 2 } else { 1 };
+
+    let weight_count = (mode.width * mode.height * plane_count) as u32;
+    let weight_bits = sequence_bits(weight_count, mode.level);
+
+    if mode.width > block_width
+        || mode.height > block_height
+        || (mode.dual_plane && partition_count == 4)
+        || weight_count > 64
+        || !(24..=96).contains(&weight_bits)
+    {
+        return error;
+    }
+
+    // Color endpoint modes of every partition.
+    let mut below_weights = 128 - weight_bits;
+    let mut modes = [0; 4];
+    let color_start = if partition_count == 1 {
+        modes[0] = bits(block, 13, 4, all);
+        17
+    } else {
+        let high_size = 3 * partition_count - 4;
+        let low = bits(block, 23, 6, all);
+
+        if low & 3 == 0 {
+            for m in modes.iter_mut() {
+                *m = (low >> 2) & 0xF;
+            }
+        } else {
+            below_weights -= high_size;
+            let encoded = low | (bits(block, below_weights, high_size, all) << 6);
+            let class = (encoded & 3) - 1;
+
+            for (i, m) in modes[..partition_count as usize].iter_mut().enumerate() {
+                let offset = 2 + partition_count as usize + 2 * i;
+                *m = ((((encoded >> (i + 2)) & 1) + class) << 2) | ((encoded >> offset) & 3);
+            }
+        }
+
+        29
+    };
+
+    let plane2_component = if mode.dual_plane {
+        below_weights -= 2;
+        Some(bits(block, below_weights, 2, all) as usize)
+    } else {
+        None
+    };
+
+    // Color endpoints.
+    let value_count: u32 = modes[..partition_count as usize]
+        .iter()
+        .map(|m| ((m >> 2) + 1) * 2)
+        .sum();
+    if value_count > 18 || below_weights < color_start {
+        return error;
+    }
+
+    let available = below_weights - color_start;
+    let level = match (4..LEVELS.len())
+        .rev()
+        .find(|l| sequence_bits(value_count, *l) <= available)
+    {
+        Some(v) => v,
+        None => return error,
+    };
+
+    let values: Vec<i32> = decode_sequence(block, color_start, value_count, level)
+        .into_iter()
+        .map(|v| unquantize_color(v, level) as i32)
+        .collect();
+
+    let mut endpoints = Vec::with_capacity(partition_count as usize);
+    let mut offset = 0;
+    for m in modes[..partition_count as usize].iter() {
+        let count = (((m >> 2) + 1) * 2) as usize;
+
+        match decode_endpoints(*m, &values[offset..offset + count]) {
+            Some(v) => endpoints.push(v),
+            None => return error,
+        }
+        offset += count;
+    }
+
+    // Weights are stored from the end of the block, with reversed bits.
+    let weights: Vec<u32> = decode_sequence(block.reverse_bits(), 0, weight_count, mode.level)
+        .into_iter()
+        .map(|v| unquantize_weight(v, mode.level))
+        .collect();
+    let planes: Vec<Vec<u32>> = (0..plane_count)
+        .map(|p| {
+            weights
+                .iter()
+                .skip(p)
+                .step_by(plane_count)
+                .copied()
+                .collect()
+        })
+        .collect();
+
+    let seed = bits(block, 13, 10, all);
+    let small_block = texel_count < 31;
+
+    let mut texels = Vec::with_capacity(texel_count);
+    for t in 0..block_height {
+        for s in 0..block_width {
+            let partition = if partition_count > 1 {
+                select_partition(seed, s as u32, t as u32, partition_count, small_block)
+            } else {
+                0
+            };
+            let (e0, e1) = endpoints[partition];
+
+            // There are at most two weight planes.
+            let mut plane_weights = [0; 2];
+            for (weight, plane) in plane_weights.iter_mut().zip(&planes) {
+                *weight = infill_weight(plane, &mode, (block_width, block_height), s, t);
+            }
+
+            let mut texel = [0; 4];
+            for c in 0..4 {
+                let weight = if plane2_component == Some(c) {
+                    plane_weights[1]
+                } else {
+                    plane_weights[0]
+                } as i32;
+
+                let c0 = e0[c] * 257;
+                let c1 = e1[c] * 257;
+                let value = (c0 * (64 - weight) + c1 * weight + 32) >> 6;
+                texel[c] = (value >> 8) as u8;
+            }
+            texels.push(texel);
+        }
+    }
+
+    texels
+}
@@ -0,0 +1,330 @@
+use super::decode_blocks;
+use image::RgbaImage;
+
+/// Modifier tables of ETC1 and ETC2 individual and differential modes.
+const MODIFIERS: [[i32; 4]; 8] = [
+    [2, 8, -2, -8],
+    [5, 17, -5, -17],
+    [9, 29, -9, -29],
+    [13, 42, -13, -42],
+    [18, 60, -18, -60],
+    [24, 80, -24, -80],
+    [33, 106, -33, -106],
+    [47, 183, -47, -183],
+];
+
+/// Distances of ETC2 T and H modes.
+const DISTANCES: [i32; 8] = [3, 6, 11, 16, 23, 32, 41, 64];
+
+/// Modifier tables of EAC alpha blocks.
+const ALPHA_MODIFIERS: [[i32; 8]; 16] = [
+    [-3, -6, -9, -15, 2, 5, 8, 14],
+    [-3, -7, -10, -13, 2, 6, 9, 12],
+    [-2, -5, -8, -13, 1, 4, 7, 12],
+    [-2, -4, -6, -13, 1, 3, 5, 12],
+    [-3, -6, -8, -12, 2, 5, 7, 11],
+    [-3, -7, -9, -11, 2, 6, 8, 10],
+    [-4, -7, -8, -11, 3, 6, 7, 10],
+    [-3, -5, -8, -11, 2, 4, 7, 10],
+    [-2, -6, -8, -10, 1, 5, 7, 9],
+    [-2, -5, -8, -10, 1, 4, 7, 9],
+    [-2, -4, -8, -10, 1, 3, 7, 9],
+    [-2, -5, -7, -10, 1, 4, 6, 9],
+    [-3, -4, -7, -10, 2, 3, 6, 9],
+    [-1, -2, -3, -10, 0, 1, 2, 9],
+    [-4, -6, -8, -9, 3, 5, 7, 8],
+    [-3, -5, -7, -9, 2, 4, 6, 8],
+];
+
+/// Variants of ETC compressed data.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum EtcFormat {
+    /// ETC1 RGB blocks of 8 bytes.
+    Etc1,
+    /// ETC2 RGB blocks of 8 bytes.
+    Etc2Rgb,
+    /// ETC2 RGBA blocks of 16 bytes, an EAC alpha block followed by an ETC2
+    /// RGB block.
+    Etc2Rgba,
+}
+
+impl EtcFormat {
+    /// Size of a single 4x4 block, in bytes.
+    pub fn block_size(self) -> usize {
+        match self {
+            Self::Etc1 | Self::Etc2Rgb => 8,
+            Self::Etc2Rgba => 16,
+        }
+    }
+}
+
+/// Returns the number of bytes of ETC data for an image of the given size.
+pub fn data_size(format: EtcFormat, width: u32, height: u32) -> usize {
+    let blocks = (width as usize).div_ceil(4) * (height as usize).div_ceil(4);
+    blocks * format.block_size()
+}
+
+/// Decodes ETC data into an image.
+///
+/// Blocks are stored row by row. `data` must be at least [`data_size`]
+/// bytes long.
+///
+/// [`data_size`]: ./fn.data_size.html
+pub fn decode(data: &[u8], format: EtcFormat, width: u32, height: u32) -> RgbaImage {
+    let block_size = format.block_size();
+
+    decode_blocks(width, height, 4, 4, |index, texels| {
+        let block = &data[index * block_size..(index + 1) * block_size];
+
+        let pixels = match format {
+            EtcFormat::Etc1 => decode_color_block(read_u64(block), false),
+            EtcFormat::Etc2Rgb => decode_color_block(read_u64(block), true),
+            EtcFormat::Etc2Rgba => {
+                let mut pixels = decode_color_block(read_u64(&block[8..]), true);
+                let alpha = decode_alpha_block(read_u64(block));
+                for (pixel, a) in pixels.iter_mut().zip(alpha.iter()) {
+                    pixel[3] = *a;
+                }
+
+                pixels
+            }
+        };
+
+        // Pixels of a block are stored column by column.
+        for (i, pixel) in pixels.iter().enumerate() {
+            texels[(i % 4) * 4 + i / 4] = *pixel;
+        }
+    })
+}
+
+/// Reads a big-endian `u64` from the first eight bytes of `data`.
+fn read_u64(data: &[u8]) -> u64 {
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(&data[..8]);
+
+    u64::from_be_bytes(bytes)
+}
+
+/// Extracts `len` bits of `block`, starting at bit `start`.
+fn bits(block: u64, start: u32, len: u32) -> i32 {
+    ((block >> start) & ((1 << len) - 1)) as i32
+}
+
+/// Expands a 4-bit channel to 8 bits.
+fn extend_4(value: i32) -> i32 {
+    (value << 4) | value
+}
+
+/// Expands a 5-bit channel to 8 bits.
+fn extend_5(value: i32) -> i32 {
+    (value << 3) | (value >> 2)
+}
+
+/// Expands a 6-bit channel to 8 bits.
+fn extend_6(value: i32) -> i32 {
+    (value << 2) | (value >> 4)
+}
+
+/// Expands a 7-bit channel to 8 bits.
+fn extend_7(value: i32) -> i32 {
+    (value << 1) | (value >> 6)
+}
+
+fn clamp(value: i32) -> u8 {
+    value.clamp(0, 255) as u8
+}
+
+/// Adds `delta` to every channel of `color`.
+fn offset(color: [i32; 3], delta: i32) -> [u8; 4] {
+    [
+        clamp(color[0] + delta),
+        clamp(color[1] + delta),
+        clamp(color[2] + delta),
+        255,
+    ]
+}
+
+/// Returns the 2-bit index of the pixel `i` of a block.
+fn pixel_index(block: u64, i: usize) -> usize {
+    let msb = (block >> (16 + i)) & 1;
+    let lsb = (block >> i) & 1;
+
+    ((msb << 1) | lsb) as usize
+}
+
+/// Decodes an ETC1 or ETC2 RGB block into 16 pixels, column by column.
+///
+/// If `etc2` is `false`, the T, H and planar modes are not considered.
+fn decode_color_block(block: u64, etc2: bool) -> [[u8; 4]; 16] {
+    let diff = block & (1 << 33) != 0;
+
+    let (base_1, base_2) = if diff {
+        let r = bits(block, 59, 5);
+        let g = bits(block, 51, 5);
+        let b = bits(block, 43, 5);
+        let r_2 = r + (bits(block, 56, 3) << 29 >> 29);
+        let g_2 = g + (bits(block, 48, 3) << 29 >> 29);
+        let b_2 = b + (bits(block, 40, 3) << 29 >> 29);
+
+        if etc2 {
+            if !(0..32).contains(&r_2) {
+                return decode_t_block(block);
+            } else if !(0..32).contains(&g_2) {
+                return decode_h_block(block);
+            } else if !(0..32).contains(&b_2) {
+                return decode_planar_block(block);
+            }
+        }
+
+        (
+            [extend_5(r), extend_5(g), extend_5(b)],
+            [extend_5(r_2 & 31), extend_5(g_2 & 31), extend_5(b_2 & 31)],
+        )
+    } else {
+        (
+            [
+                extend_4(bits(block, 60, 4)),
+                extend_4(bits(block, 52, 4)),
+                extend_4(bits(block, 44, 4)),
+            ],
+            [
+                extend_4(bits(block, 56, 4)),
+                extend_4(bits(block, 48, 4)),
+                extend_4(bits(block, 40, 4)),
+            ],
+        )
+    };
+
+    let table_1 = MODIFIERS[bits(block, 37, 3) as usize];
+    let table_2 = MODIFIERS[bits(block, 34, 3) as usize];
+    let flip = block & (1 << 32) != 0;
+
+    let mut pixels = [[0; 4]; 16];
+    for (i, pixel) in pixels.iter_mut().enumerate() {
+        let (x, y) = (i / 4, i % 4);
+        let first = if flip { y < 2 } else { x < 2 };
+
+        let (base, table) = if first {
+            (base_1, table_1)
+        } else {
+            (base_2, table_2)
+        };
+        *pixel = offset(base, table[pixel_index(block, i)]);
+    }
+
+    pixels
+}
+
+/// Decodes an ETC2 block in T mode.
+fn decode_t_block(block: u64) -> [[u8; 4]; 16] {
+    let color_1 = [
+        extend_4((bits(block, 59, 2) << 2) | bits(block, 56, 2)),
+        extend_4(bits(block, 52, 4)),
+        extend_4(bits(block, 48, 4)),
+    ];
+    let color_2 = [
+        extend_4(bits(block, 44, 4)),
+        extend_4(bits(block, 40, 4)),
+        extend_4(bits(block, 36, 4)),
+    ];
+    let distance = DISTANCES[((bits(block, 34, 2) << 1) | bits(block, 32, 1)) as usize];
+
+    let paint = [
+        offset(color_1, 0),
+        offset(color_2, distance),
+        offset(color_2, 0),
+        offset(color_2, -distance),
+    ];
+
+    let mut pixels = [[0; 4]; 16];
+    for (i, pixel) in pixels.iter_mut().enumerate() {
+        *pixel = paint[pixel_index(block, i)];
+    }
+
+    pixels
+}
+
+/// Decodes an ETC2 block in H mode.
+fn decode_h_block(block: u64) -> [[u8; 4]; 16] {
+    let color_1 = [
+        extend_4(bits(block, 59, 4)),
+        extend_4((bits(block, 56, 3) << 1) | bits(block, 52, 1)),
+        extend_4((bits(block, 51, 1) << 3) | bits(block, 47, 3)),
+    ];
+    let color_2 = [
+        extend_4(bits(block, 43, 4)),
+        extend_4(bits(block, 39, 4)),
+        extend_4(bits(block, 35, 4)),
+    ];
+
+    let value_1 = (color_1[0] << 16) | (color_1[1] << 8) | color_1[2];
+    let value_2 = (color_2[0] << 16) | (color_2[1] << 8) | color_2[2];
+    let index = (bits(block, 34, 1) << 2) | (bits(block, 32, 1) << 1) | (value_1 >= value_2) as i32;
+    let distance = DISTANCES[index as usize];
+
+    let paint = [
+        offset(color_1, distance),
+        offset(color_1, -distance),
+        offset(color_2, distance),
+        offset(color_2, -distance),
+    ];
+
+    let mut pixels = [[0; 4]; 16];
+    for (i, pixel) in pixels.iter_mut().enumerate() {
+        *pixel = paint[pixel_index(block, i)];
+    }
+
+    pixels
+}
+
+/// Decodes an ETC2 block in planar mode.
+fn decode_planar_block(block: u64) -> [[u8; 4]; 16] {
+    let origin = [
+        extend_6(bits(block, 57, 6)),
+        extend_7((bits(block, 56, 1) << 6) | bits(block, 49, 6)),
+        extend_6((bits(block, 48, 1) << 5) | (bits(block, 43, 2) << 3) | bits(block, 39, 3)),
+    ];
+    let horizontal = [
+        extend_6((bits(block, 34, 5) << 1) | bits(block, 32, 1)),
+        extend_7(bits(block, 25, 7)),
+        extend_6(bits(block, 19, 6)),
+    ];
+    let vertical = [
+        extend_6(bits(block, 13, 6)),
+        extend_7(bits(block, 6, 7)),
+        extend_6(bits(block, 0, 6)),
+    ];
+
+    let mut pixels = [[0; 4]; 16];
+    for (i, pixel) in pixels.iter_mut().enumerate() {
+        let (x, y) = ((i / 4) as i32, (i % 4) as i32);
+
+        for c in 0..3 {
+            pixel[c] = clamp(
+                (x * (horizontal[c] - origin[c])
+                    + y * (vertical[c] - origin[c])
+                    + 4 * origin[c]
+                    + 2)
+                    >> 2,
+            );
+        }
+        pixel[3] = 255;
+    }
+
+    pixels
+}
+
+/// Decodes an EAC alpha block into 16 alpha values, column by column.
+fn decode_alpha_block(block: u64) -> [u8; 16] {
+    let base = bits(block, 56, 8);
+    let multiplier = bits(block, 52, 4);
+    let table = ALPHA_MODIFIERS[bits(block, 48, 4) as usize];
+
+    let mut alpha = [0; 16];
+    for (i, a) in alpha.iter_mut().enumerate() {
+        let index = bits(block, 45 - 3 * i as u32, 3) as usize;
+        *a = clamp(base + table[index] * multiplier);
+    }
+
+    alpha
+}
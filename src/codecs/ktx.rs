@@ -0,0 +1,148 @@
+use super::{etc::EtcFormat, BlockFormat};
+use crate::error::Error;
+
+/// Identifier every KTX 1 file starts with.
+const IDENTIFIER: [u8; 12] = [
+    0xAB, b'K', b'T', b'X', b' ', b'1', b'1', 0xBB, b'\r', b'\n', 0x1A, b'\n',
+];
+
+/// Size of the KTX 1 header, including the identifier.
+const HEADER_SIZE: usize = 64;
+
+/// Block footprints of the ASTC internal formats, in the order of their
+/// OpenGL constants.
+const ASTC_FOOTPRINTS: [(u32, u32); 14] = [
+    (4, 4),
+    (5, 4),
+    (5, 5),
+    (6, 5),
+    (6, 6),
+    (8, 5),
+    (8, 6),
+    (8, 8),
+    (10, 5),
+    (10, 6),
+    (10, 8),
+    (10, 10),
+    (12, 10),
+    (12, 12),
+];
+
+/// The mip levels of a KTX texture.
+pub struct Ktx<'a> {
+    /// Block format of the image data.
+    pub format: BlockFormat,
+    /// Width of the base level, in pixels.
+    pub width: u32,
+    /// Height of the base level, in pixels.
+    pub height: u32,
+    /// Compressed image data of every mip level, starting with the base level.
+    pub levels: Vec<&'a [u8]>,
+}
+
+impl Ktx<'_> {
+    /// Returns the width and height of a mip level, in pixels.
+    pub fn level_size(&self, level: usize) -> (u32, u32) {
+        ((self.width >> level).max(1), (self.height >> level).max(1))
+    }
+}
+
+/// Returns the block format of an OpenGL internal format, if it is supported.
+fn block_format(internal_format: u32) -> Option<BlockFormat> {
+    match internal_format {
+        // GL_ETC1_RGB8_OES
+        0x8D64 => Some(BlockFormat::Etc(EtcFormat::Etc1)),
+        // GL_COMPRESSED_RGB8_ETC2 and its sRGB variant
+        0x9274 | 0x9275 => Some(BlockFormat::Etc(EtcFormat::Etc2Rgb)),
+        // GL_COMPRESSED_RGBA8_ETC2_EAC and its sRGB variant
+        0x9278 | 0x9279 => Some(BlockFormat::Etc(EtcFormat::Etc2Rgba)),
+        // GL_COMPRESSED_RGBA_ASTC_*_KHR
+        0x93B0..=0x93BD => {
+            let (w, h) = ASTC_FOOTPRINTS[(internal_format - 0x93B0) as usize];
+            Some(BlockFormat::Astc(w, h))
+        }
+        // GL_COMPRESSED_SRGB8_ALPHA8_ASTC_*_KHR
+        0x93D0..=0x93DD => {
+            let (w, h) = ASTC_FOOTPRINTS[(internal_format - 0x93D0) as usize];
+            Some(BlockFormat::Astc(w, h))
+        }
+        _ => None,
+    }
+}
+
+/// Parses a KTX 1 container and returns its mip levels.
+///
+/// Only block-compressed formats that have a decoder are supported.
+///
+/// ## Errors
+///
+/// [`Error::KtxError`] is returned if the container is invalid, truncated
+/// or holds an unsupported format.
+///
+/// [`Error::KtxError`]: ./error/enum.Error.html#variant.KtxError
+pub fn parse(data: &[u8]) -> Result<Ktx<'_>, Error> {
+    if data.len() < HEADER_SIZE || data[..12] != IDENTIFIER {
+        return Err(Error::KtxError("Invalid KTX header".to_string()));
+    }
+
+    // Files written on big-endian machines store the endianness marker
+    // byte-swapped.
+    let big_endian = data[12..16] == [4, 3, 2, 1];
+    let read_u32 = |offset: usize| -> Result<u32, Error> {
+        let bytes = data
+            .get(offset..offset + 4)
+            .ok_or_else(|| Error::KtxError("KTX data is truncated".to_string()))?;
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+
+        Ok(if big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        })
+    };
+
+    let internal_format = read_u32(28)?;
+    let width = read_u32(36)?;
+    let height = read_u32(40)?.max(1);
+    let level_count = read_u32(56)?.max(1) as usize;
+    let key_value_size = read_u32(60)? as usize;
+
+    // Every level is at least half as large as the previous one down to 1x1,
+    // so 32-bit sizes have at most 32 levels, each taking at least 4 bytes.
+    if level_count > 32 || level_count > (data.len() - HEADER_SIZE) / 4 {
+        return Err(Error::KtxError(format!(
+            "Invalid KTX mip level count {}",
+            level_count
+        )));
+    }
+
+    let format = block_format(internal_format).ok_or_else(|| {
+        Error::KtxError(format!(
+            "Unsupported KTX internal format 0x{:X}",
+            internal_format
+        ))
+    })?;
+
+    let mut ktx = Ktx {
+        format,
+        width,
+        height,
+        levels: Vec::with_capacity(level_count),
+    };
+
+    let mut offset = HEADER_SIZE + key_value_size;
+    for level in 0..level_count {
+        let (width, height) = ktx.level_size(level);
+        let size = read_u32(offset)? as usize;
+        let image = data
+            .get(offset + 4..offset + 4 + size)
+            .filter(|d| d.len() >= format.data_size(width, height))
+            .ok_or_else(|| Error::KtxError("KTX data is truncated".to_string()))?;
+        ktx.levels.push(image);
+
+        // Every level is padded to a multiple of four bytes.
+        offset += 4 + size.next_multiple_of(4);
+    }
+
+    Ok(ktx)
+}
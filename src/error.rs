@@ -1,41 +1,127 @@
+use image::ImageError;
 use std::{
     error::Error as StdError,
     fmt::{Display, Formatter, Result as FmtResult},
     io::Error as IoError,
+    path::PathBuf,
 };
 
 /// A common error variant returned by this library.
 #[derive(Debug)]
 pub enum Error {
-    /// Returned when an unknown pixel is attempted to be processed.
-    UnknownPixel(String),
+    /// Returned when an unknown pixel type is attempted to be processed.
+    UnknownPixel {
+        /// Tag of the texture containing the pixel.
+        tag: u8,
+        /// The unknown pixel type.
+        pixel_type: u8,
+        /// Offset of the pixel from the start of the decompressed data.
+        offset: usize,
+    },
     /// Returned when decompression of a file fails.
     DecompressionError(String),
+    /// Returned when an embedded or external KTX texture is invalid or uses
+    /// an unsupported format.
+    KtxError(String),
+    /// Returned when a texture image required to cut sprites, or an external
+    /// texture file referenced by a `_tex.sc` file, does not exist.
+    MissingTexture {
+        /// The path where the texture was expected.
+        path: PathBuf,
+    },
+    /// Returned when sprites are cut with fewer texture sheets than the
+    /// textures of the `.sc` file.
+    MissingSheet {
+        /// Index of the first missing sheet.
+        index: usize,
+    },
+    /// Returned when a movie clip that is not in the `.sc` file is rendered.
+    UnknownClip {
+        /// ID of the movie clip.
+        id: u16,
+    },
+    /// Returned when a tag is not valid at its position in the data.
+    BadTag {
+        /// The invalid tag.
+        tag: u8,
+        /// Offset of the tag from the start of the decompressed data.
+        offset: usize,
+    },
+    /// Returned when the data ends before a read of `wanted` bytes at
+    /// `offset` could be completed.
+    UnexpectedEof {
+        /// Offset of the read from the start of the decompressed data.
+        offset: usize,
+        /// Number of bytes the read needed.
+        wanted: usize,
+    },
+    /// Returned when the hash of the decompressed data does not match the
+    /// hash stored in the file header.
+    HashMismatch {
+        /// The hash stored in the file header.
+        expected: Vec<u8>,
+        /// The hash of the decompressed data.
+        found: Vec<u8>,
+    },
     /// Returned when an IO operation fails.
-    IoError(String),
+    IoError(IoError),
+    /// Returned when opening, decoding or saving an image fails.
+    ImageError(ImageError),
     /// Returned when a non-specific, miscellaneous error occurs.
     ///
     /// It is also returned when a string is used to create an `Error` directly.
     Other(String),
 }
 
-impl Error {
-    /// Returns a reference to the inner string of the error.
-    pub fn inner(&self) -> &String {
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
         match self {
-            Self::UnknownPixel(e) => e,
-            Self::DecompressionError(e) => e,
-            Self::IoError(e) => e,
-            Self::Other(e) => e,
+            Self::IoError(e) => Some(e),
+            Self::ImageError(e) => Some(e),
+            _ => None,
         }
     }
 }
 
-impl StdError for Error {}
-
 impl Display for Error {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
-        write!(f, "{}", self.inner())
+        match self {
+            Self::UnknownPixel {
+                tag,
+                pixel_type,
+                offset,
+            } => write!(
+                f,
+                "Unknown pixel type ({}) in texture tag {} at offset {}",
+                pixel_type, tag, offset
+            ),
+            Self::DecompressionError(e) => write!(f, "{}", e),
+            Self::KtxError(e) => write!(f, "{}", e),
+            Self::MissingTexture { path } => {
+                write!(f, "Expected extracted png image `{}`", path.display())
+            }
+            Self::MissingSheet { index } => {
+                write!(f, "Expected a texture sheet with index {}", index)
+            }
+            Self::UnknownClip { id } => write!(f, "No movie clip with ID {}", id),
+            Self::BadTag { tag, offset } => {
+                write!(f, "Unexpected tag {} at offset {}", tag, offset)
+            }
+            Self::UnexpectedEof { offset, wanted } => write!(
+                f,
+                "Unexpected end of data at offset {} (wanted {} byte(s))",
+                offset, wanted
+            ),
+            Self::HashMismatch { expected, found } => write!(
+                f,
+                "Hash mismatch (expected {}, found {})",
+                hex::encode(expected),
+                hex::encode(found)
+            ),
+            Self::IoError(e) => write!(f, "IO error: {}", e),
+            Self::ImageError(e) => write!(f, "Image error: {}", e),
+            Self::Other(e) => write!(f, "{}", e),
+        }
     }
 }
 
@@ -53,6 +139,12 @@ impl From<String> for Error {
 
 impl From<IoError> for Error {
     fn from(error: IoError) -> Self {
-        Self::IoError(error.to_string())
+        Self::IoError(error)
+    }
+}
+
+impl From<ImageError> for Error {
+    fn from(error: ImageError) -> Self {
+        Self::ImageError(error)
     }
 }
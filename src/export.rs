@@ -0,0 +1,452 @@
+use crate::{error::Error, extractors::sc::TextureVariant, render::Animation};
+use image::{
+    codecs::{
+        bmp::BmpEncoder,
+        gif::{GifEncoder, Repeat},
+        png::{CompressionType, FilterType, PngEncoder},
+        qoi::QoiEncoder,
+        tga::TgaEncoder,
+        webp::WebPEncoder,
+    },
+    ColorType, Delay, Frame, ImageEncoder, Rgba, RgbaImage,
+};
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// Image format extracted images are saved in.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum OutputFormat {
+    /// PNG images, the default.
+    #[default]
+    Png,
+    /// Lossless WebP images.
+    WebP,
+    /// Uncompressed TGA images.
+    Tga,
+    /// Uncompressed BMP images.
+    Bmp,
+    /// QOI images.
+    Qoi,
+}
+
+impl OutputFormat {
+    /// Returns the file extension of the format, without the leading dot.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::WebP => "webp",
+            Self::Tga => "tga",
+            Self::Bmp => "bmp",
+            Self::Qoi => "qoi",
+        }
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "png" => Ok(Self::Png),
+            "webp" => Ok(Self::WebP),
+            "tga" => Ok(Self::Tga),
+            "bmp" => Ok(Self::Bmp),
+            "qoi" => Ok(Self::Qoi),
+            _ => Err("Format must be one of `png`, `webp`, `tga`, `bmp` and `qoi`."),
+        }
+    }
+}
+
+/// Compression level of PNG images.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum PngCompression {
+    /// Fast, minimal compression, the default.
+    #[default]
+    Fast,
+    /// Balanced compression.
+    Default,
+    /// Best compression, which is the slowest.
+    Best,
+}
+
+impl FromStr for PngCompression {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "fast" => Ok(Self::Fast),
+            "default" => Ok(Self::Default),
+            "best" => Ok(Self::Best),
+            _ => Err("PNG compression must be one of `fast`, `default` and `best`."),
+        }
+    }
+}
+
+impl From<PngCompression> for CompressionType {
+    fn from(compression: PngCompression) -> Self {
+        match compression {
+            PngCompression::Fast => Self::Fast,
+            PngCompression::Default => Self::Default,
+            PngCompression::Best => Self::Best,
+        }
+    }
+}
+
+/// Format animations of movie clips are saved in.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum AnimationFormat {
+    /// Animated PNG images, the default.
+    #[default]
+    Apng,
+    /// GIF images. They only support fully opaque or transparent pixels.
+    Gif,
+    /// Lossless animated WebP images.
+    WebP,
+}
+
+impl AnimationFormat {
+    /// Returns the file extension of the format, without the leading dot.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Apng => "png",
+            Self::Gif => "gif",
+            Self::WebP => "webp",
+        }
+    }
+}
+
+impl FromStr for AnimationFormat {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "apng" => Ok(Self::Apng),
+            "gif" => Ok(Self::Gif),
+            "webp" => Ok(Self::WebP),
+            _ => Err("Animation format must be one of `apng`, `gif` and `webp`."),
+        }
+    }
+}
+
+impl From<PngCompression> for png::Compression {
+    fn from(compression: PngCompression) -> Self {
+        match compression {
+            PngCompression::Fast => Self::Fast,
+            PngCompression::Default => Self::Default,
+            PngCompression::Best => Self::Best,
+        }
+    }
+}
+
+/// How sprites of shapes without an export name are named.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum SpriteFallback {
+    /// `{file}_sprite_{index}`, with the index of the shape in the file.
+    #[default]
+    Index,
+    /// `{file}_shape_{id}`, with the ID of the shape.
+    ShapeId,
+    /// Sprites of shapes without an export name are not saved.
+    Skip,
+}
+
+impl FromStr for SpriteFallback {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "index" => Ok(Self::Index),
+            "id" => Ok(Self::ShapeId),
+            "skip" => Ok(Self::Skip),
+            _ => Err("Sprite fallback must be one of `index`, `id` and `skip`."),
+        }
+    }
+}
+
+/// Options controlling how extracted images are written.
+#[derive(Clone, Debug, Default)]
+pub struct ExportOptions {
+    /// Image format of the saved images.
+    pub format: OutputFormat,
+    /// Compression level of PNG images. Other formats ignore it.
+    pub png_compression: PngCompression,
+    /// Texture variant sprites are cut from.
+    ///
+    /// If `None`, the first variant with images present is used.
+    pub texture_variant: Option<TextureVariant>,
+    /// Names all sprites with `sprite_fallback` instead of their export names.
+    pub numbered_sprites: bool,
+    /// Naming of sprites of shapes without an export name.
+    pub sprite_fallback: SpriteFallback,
+    /// Renders exported movie clips into frame sequences next to the sprites.
+    pub movie_clips: bool,
+    /// Renders exported movie clips into animations next to the sprites.
+    pub animations: bool,
+    /// Format of the animations of movie clips.
+    pub animation_format: AnimationFormat,
+    /// Renders movie clips without the color transforms tinting and fading
+    /// their children, giving the untinted base art.
+    pub ignore_color_transforms: bool,
+    /// Only renders the sub-animation of movie clips starting at the frame
+    /// with this label, such as `idle` or `attack`.
+    ///
+    /// Movie clips without a frame with the label are skipped.
+    pub frame_label: Option<String>,
+    /// Saves the mip levels stored in textures next to their base image.
+    pub mipmaps: bool,
+    /// Converts images from premultiplied to straight alpha before saving.
+    ///
+    /// Textures are stored premultiplied. Straight alpha avoids dark fringes
+    /// around semi-transparent edges in most image viewers and editors. When
+    /// cutting sprites, the texture images are expected to have been saved
+    /// with the same option.
+    pub unpremultiply: bool,
+}
+
+/// Multiplies the color channels of every pixel by its alpha.
+pub fn premultiply(image: &mut RgbaImage) {
+    for pixel in image.pixels_mut() {
+        let a = pixel[3] as u32;
+        for c in 0..3 {
+            pixel[c] = ((pixel[c] as u32 * a + 127) / 255) as u8;
+        }
+    }
+}
+
+/// Divides the color channels of every pixel by its alpha.
+///
+/// Fully transparent pixels become transparent black.
+pub fn unpremultiply(image: &mut RgbaImage) {
+    for pixel in image.pixels_mut() {
+        let a = pixel[3] as u32;
+        if a == 0 {
+            *pixel = Rgba([0, 0, 0, 0]);
+            continue;
+        }
+
+        for c in 0..3 {
+            pixel[c] = ((pixel[c] as u32 * 255 + a / 2) / a).min(255) as u8;
+        }
+    }
+}
+
+/// Encodes `image` in the format selected by `options` and writes it to `path`.
+fn encode(image: &RgbaImage, path: &Path, options: &ExportOptions) -> Result<(), Error> {
+    let mut file = BufWriter::new(File::create(path)?);
+    let (width, height) = image.dimensions();
+    let data = image.as_raw();
+
+    match options.format {
+        OutputFormat::Png => PngEncoder::new_with_quality(
+            &mut file,
+            options.png_compression.into(),
+            FilterType::Adaptive,
+        )
+        .write_image(data, width, height, ColorType::Rgba8)?,
+        OutputFormat::WebP => WebPEncoder::new_lossless(&mut file).write_image(
+            data,
+            width,
+            height,
+            ColorType::Rgba8,
+        )?,
+        OutputFormat::Tga => {
+            TgaEncoder::new(&mut file).write_image(data, width, height, ColorType::Rgba8)?
+        }
+        OutputFormat::Bmp => {
+            BmpEncoder::new(&mut file).write_image(data, width, height, ColorType::Rgba8)?
+        }
+        OutputFormat::Qoi => {
+            QoiEncoder::new(&mut file).write_image(data, width, height, ColorType::Rgba8)?
+        }
+    }
+
+    file.flush()?;
+    Ok(())
+}
+
+/// Saves a premultiplied image, applying `options`.
+///
+/// `name` is the path of the image without an extension. The extension of
+/// `options.format` is appended to it and the full path is returned.
+pub(crate) fn save_image(
+    image: &RgbaImage,
+    name: &str,
+    options: &ExportOptions,
+) -> Result<PathBuf, Error> {
+    let path = PathBuf::from(format!("{}.{}", name, options.format.extension()));
+
+    if options.unpremultiply {
+        let mut image = image.clone();
+        unpremultiply(&mut image);
+        encode(&image, &path, options)?;
+    } else {
+        encode(image, &path, options)?;
+    }
+
+    Ok(path)
+}
+
+/// Saves a rendered movie clip as an animation, applying `options`.
+///
+/// The animation is saved in `options.animation_format`. `name` is the path
+/// of the animation without an extension, like for the frames of the clip.
+/// The extension of the format is appended to it and the full path is
+/// returned.
+///
+/// Frames are shown for the duration given by the frame rate of the clip.
+/// Animations loop forever.
+///
+/// ## Errors
+///
+/// If the animation has no frames, [`Error::Other`] is returned.
+///
+/// If encoding or writing the animation fails, [`Error::ImageError`] or
+/// [`Error::IoError`] is returned.
+///
+/// [`Error::Other`]: ./enum.Error.html#variant.Other
+/// [`Error::ImageError`]: ./enum.Error.html#variant.ImageError
+/// [`Error::IoError`]: ./enum.Error.html#variant.IoError
+pub fn save_animation(
+    animation: &Animation,
+    name: &str,
+    options: &ExportOptions,
+) -> Result<PathBuf, Error> {
+    if animation.frames.is_empty() {
+        return Err(Error::Other("Animation has no frames".to_string()));
+    }
+
+    let path = PathBuf::from(format!("{}.{}", name, options.animation_format.extension()));
+
+    let frames: Vec<RgbaImage> = animation
+        .frames
+        .iter()
+        .map(|f| {
+            let mut image = f.image.clone();
+            if options.unpremultiply {
+                unpremultiply(&mut image);
+            }
+
+            image
+        })
+        .collect();
+
+    // Durations of the frames, in milliseconds. They are rounded so that the
+    // animation does not drift from the frame rate.
+    let fps = animation.fps.max(1) as u32;
+    let durations: Vec<u32> = (0..frames.len() as u32)
+        .map(|x| ((x + 1) * 1000 + fps / 2) / fps - (x * 1000 + fps / 2) / fps)
+        .collect();
+
+    let mut file = BufWriter::new(File::create(&path)?);
+    match options.animation_format {
+        AnimationFormat::Apng => encode_apng(&mut file, &frames, fps, options)?,
+        AnimationFormat::Gif => {
+            let mut encoder = GifEncoder::new_with_speed(&mut file, 10);
+            encoder.set_repeat(Repeat::Infinite)?;
+            encoder.encode_frames(frames.into_iter().zip(&durations).map(|(image, &ms)| {
+                Frame::from_parts(image, 0, 0, Delay::from_numer_denom_ms(ms, 1))
+            }))?;
+        }
+        AnimationFormat::WebP => encode_animated_webp(&mut file, &frames, &durations)?,
+    }
+
+    file.flush()?;
+    Ok(path)
+}
+
+/// Writes `frames` as an animated PNG.
+fn encode_apng<W: Write>(
+    writer: W,
+    frames: &[RgbaImage],
+    fps: u32,
+    options: &ExportOptions,
+) -> Result<(), Error> {
+    let (width, height) = frames[0].dimensions();
+    let mut encoder = png::Encoder::new(writer, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_compression(options.png_compression.into());
+    encoder
+        .set_animated(frames.len() as u32, 0)
+        .map_err(png_error)?;
+    encoder.set_frame_delay(1, fps as u16).map_err(png_error)?;
+
+    let mut writer = encoder.write_header().map_err(png_error)?;
+    for frame in frames {
+        writer.write_image_data(frame.as_raw()).map_err(png_error)?;
+    }
+
+    writer.finish().map_err(png_error)
+}
+
+/// Converts an error of the `png` crate into an `Error`.
+fn png_error(error: png::EncodingError) -> Error {
+    match error {
+        png::EncodingError::IoError(e) => Error::IoError(e),
+        e => Error::Other(e.to_string()),
+    }
+}
+
+/// Writes `frames` as a lossless animated WebP, showing each of them for the
+/// number of milliseconds in `durations`.
+///
+/// Every frame is encoded as a still lossless image, whose `VP8L` chunk is
+/// placed in an `ANMF` chunk of an extended WebP file.
+fn encode_animated_webp<W: Write>(
+    mut writer: W,
+    frames: &[RgbaImage],
+    durations: &[u32],
+) -> Result<(), Error> {
+    let (width, height) = frames[0].dimensions();
+
+    let mut body = b"WEBP".to_vec();
+
+    // Animation and alpha flags, followed by the canvas size.
+    let mut vp8x = vec![0x12, 0, 0, 0];
+    vp8x.extend_from_slice(&(width - 1).to_le_bytes()[..3]);
+    vp8x.extend_from_slice(&(height - 1).to_le_bytes()[..3]);
+    write_chunk(&mut body, b"VP8X", &vp8x);
+
+    // Transparent background, looping forever.
+    write_chunk(&mut body, b"ANIM", &[0; 6]);
+
+    for (frame, &duration) in frames.iter().zip(durations) {
+        let mut still = Vec::new();
+        WebPEncoder::new_lossless(&mut still).write_image(
+            frame.as_raw(),
+            width,
+            height,
+            ColorType::Rgba8,
+        )?;
+
+        // Frame at (0, 0) of the canvas, which is not blended with the
+        // previous frame, followed by the chunks of the still image.
+        let mut anmf = vec![0; 6];
+        anmf.extend_from_slice(&(width - 1).to_le_bytes()[..3]);
+        anmf.extend_from_slice(&(height - 1).to_le_bytes()[..3]);
+        anmf.extend_from_slice(&duration.min(0xFF_FFFF).to_le_bytes()[..3]);
+        anmf.push(0x02);
+        anmf.extend_from_slice(&still[12..]);
+        write_chunk(&mut body, b"ANMF", &anmf);
+    }
+
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&(body.len() as u32).to_le_bytes())?;
+    writer.write_all(&body)?;
+
+    Ok(())
+}
+
+/// Appends a RIFF chunk to `buf`, padded to an even size.
+fn write_chunk(buf: &mut Vec<u8>, fourcc: &[u8; 4], data: &[u8]) {
+    buf.extend_from_slice(fourcc);
+    buf.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    buf.extend_from_slice(data);
+    if data.len() % 2 == 1 {
+        buf.push(0);
+    }
+}
@@ -1,5 +1,4 @@
 use crate::{error::Error, utils::decompress};
-use colored::Colorize;
 use std::{fs, path::Path};
 
 /// Processes encoded, raw `.csv` file data.
@@ -16,13 +15,11 @@ use std::{fs, path::Path};
 /// [`Error::DecompressionError`]: ./error/enum.Error.html#variant.DecompressionError
 /// [`Error::IoError`]: ./error/enum.Error.html#variant.IoError
 pub fn process_csv(data: &[u8], file_name: &str, out_dir: &Path) -> Result<(), Error> {
-    let decompressed = match decompress(data) {
+    let (_, decompressed) = match decompress(data) {
         Ok(c) => c,
         Err(e) => return Err(e),
     };
 
-    println!("\nExtracting {} file...", file_name.green().bold());
-
     fs::write(out_dir.join(file_name), decompressed.get_ref())?;
 
     Ok(())
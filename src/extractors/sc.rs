@@ -1,34 +1,30 @@
-use crate::{error::Error, utils::Reader};
-use colored::Colorize;
+use crate::{
+    error::Error,
+    export::{premultiply, save_animation, save_image, ExportOptions, SpriteFallback},
+    model::{ScFile, TWIPS_PER_PIXEL},
+    observer::{Event, Observer},
+    render::render_movie_clip,
+};
 use conv::ValueInto;
-use image::{imageops, GenericImage, GenericImageView, GrayImage, ImageBuffer, Pixel, RgbaImage};
+use image::{
+    error::{ImageError, ParameterError, ParameterErrorKind},
+    imageops, GenericImageView, GrayImage, ImageBuffer, Pixel, Rgba, RgbaImage,
+};
 use imageproc::{
     definitions::{Clamp, Image},
-    drawing::{draw_convex_polygon_mut, Point as Point2D},
+    drawing::draw_polygon_mut,
     geometric_transformations::{warp_into, Interpolation, Projection},
+    point::Point as Point2D,
 };
 use rayon::prelude::*;
 use std::{
     cmp::Ordering,
-    io::Cursor,
+    collections::{HashMap, HashSet},
+    fs,
     path::Path,
-    sync::{Arc, Mutex},
+    str::FromStr,
 };
 
-/// Struct to represent a sheet item.
-#[derive(Debug)]
-struct SheetItem {
-    x: u32,
-    y: u32,
-    divider: u32,
-}
-
-impl SheetItem {
-    fn new(x: u32, y: u32, divider: u32) -> Self {
-        Self { x, y, divider }
-    }
-}
-
 /// Struct to represent a 2-dimensional point.
 #[derive(Debug, PartialEq)]
 struct Point {
@@ -117,255 +113,463 @@ impl Rotation {
     }
 }
 
-/// Processes extracted `.sc` file data.
+/// Variant of the `_tex.sc` file sprites are cut from.
 ///
-/// This function does NOT process files with `.sc` extension. It process
-/// the file generated after using QuickBMS on the `.sc` file. The png images
-/// extracted from `_tex.sc` file corresponding to this `.sc` file must be
-/// present in `png_dir`.
+/// Some games ship textures of multiple resolutions for a single `.sc` file.
+/// For `ui.sc`, they are stored in `ui_tex.sc`, `ui_highres_tex.sc` and
+/// `ui_lowres_tex.sc`. All of them share the coordinates of the `.sc` file,
+/// which are scaled to the resolution of the sheet images.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TextureVariant {
+    /// The `_tex.sc` file.
+    Default,
+    /// The `_highres_tex.sc` file.
+    HighRes,
+    /// The `_lowres_tex.sc` file.
+    LowRes,
+}
+
+impl TextureVariant {
+    /// Returns the suffix of the name of the `_tex.sc` file, which follows the
+    /// name of the `.sc` file.
+    pub fn suffix(self) -> &'static str {
+        match self {
+            Self::Default => "_tex",
+            Self::HighRes => "_highres_tex",
+            Self::LowRes => "_lowres_tex",
+        }
+    }
+}
+
+impl FromStr for TextureVariant {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "default" => Ok(Self::Default),
+            "highres" => Ok(Self::HighRes),
+            "lowres" => Ok(Self::LowRes),
+            _ => Err("Texture variant must be one of `default`, `highres` and `lowres`."),
+        }
+    }
+}
+
+/// A sprite cut from texture sheets using `.sc` file data.
+#[derive(Clone, Debug)]
+pub struct Sprite {
+    /// ID of the shape the sprite was cut from.
+    pub shape_id: u16,
+    /// The sprite image.
+    pub image: RgbaImage,
+    /// Position of the origin of the shape in the image, in pixels.
+    pub origin: (u32, u32),
+    /// Horizontal and vertical pixels of the image per pixel of the shape.
+    ///
+    /// It depends on the resolution of the texture sheets the sprite was cut
+    /// from.
+    pub scale: (f32, f32),
+}
+
+/// Processes compressed, raw `.sc` file data.
+///
+/// The data passed here must be the original **compressed/raw** `.sc` file.
+/// The images extracted from `_tex.sc` file corresponding to this `.sc`
+/// file must be present in `png_dir`, in the format of `options.format`. For
+/// `ui.sc`, the images `ui_tex.png`, `ui_tex_.png` and so on are expected.
+/// The [`TextureVariant`] is selected with `options.texture_variant`, and if
+/// it is `None`, the first variant with images present is used, trying the
+/// default, high and low resolution variants in this order.
 ///
 /// A single `.sc` file contains data for multiple sprites. All of the
-/// sprites are extracted and saved by this process in the `out_dir`.
+/// sprites are cut with [`cut_sprites`] and saved by this process in the
+/// `out_dir`, in the same format. Sprites are named after the exports
+/// reaching their shapes, as returned by [`ScFile::shape_names`], unless
+/// `options.numbered_sprites` is set. Other sprites are named according to
+/// `options.sprite_fallback`, which defaults to `ui_sprite_00` and so on for
+/// `ui.sc`. If `options.unpremultiply` is set, the
+/// images are expected to have straight alpha, like [`process_tex`] saves
+/// them with the same options, and the sprites are saved with straight alpha
+/// too.
 ///
-/// `parallelize` tells if the directory files are processed parallelly. It is
-/// simply used to control the stdout output. Within this function, sprites are
-/// always processed parallelly to increase efficiency.
+/// If `options.movie_clips` is set, the exported movie clips are also
+/// rendered with [`render_movie_clip`]. The frames of each of them are saved
+/// in a directory named after the export, such as `hero_idle/hero_idle_0`.
+/// Their color transforms are applied unless
+/// `options.ignore_color_transforms` is set. If `options.animations` is set, they are saved as animations with
+/// [`save_animation`] instead, such as `hero_idle/hero_idle.gif`. With
+/// `options.frame_label`, only the sub-animations starting at that label are
+/// rendered, such as `hero_idle/hero_idle_idle.gif`.
+///
+/// Every saved sprite and any warnings are reported to `observer`. Within
+/// this function, sprites are always processed parallelly to increase
+/// efficiency.
 ///
 /// ## Errors
 ///
-/// If the png images are not present, [`Error::Other`] is returned.
+/// If the texture images are not present, [`Error::MissingTexture`] is returned.
+///
+/// If a tag is invalid at its position, such as a region referring to a
+/// missing texture, [`Error::BadTag`] is returned.
 ///
 /// If decompression is unsuccessful, [`Error::DecompressionError`] is returned.
 ///
+/// If the decompressed data is truncated, [`Error::UnexpectedEof`] is returned.
+///
 /// If `out_dir` does not exist or if reading images from `png_dir` is not
-/// possible, [`Error::IoError`] is returned.
+/// possible, [`Error::ImageError`] is returned.
 ///
+/// [`TextureVariant`]: ./enum.TextureVariant.html
+/// [`cut_sprites`]: ./fn.cut_sprites.html
+/// [`ScFile::shape_names`]: ./model/struct.ScFile.html#method.shape_names
+/// [`process_tex`]: ./fn.process_tex.html
+/// [`render_movie_clip`]: ./fn.render_movie_clip.html
+/// [`save_animation`]: ./fn.save_animation.html
 /// [`Error::DecompressionError`]: ./error/enum.Error.html#variant.DecompressionError
-/// [`Error::Other`]: ./error/enum.Error.html#variant.Other
-/// [`Error::IoError`]: ./error/enum.Error.html#variant.IoError
+/// [`Error::MissingTexture`]: ./error/enum.Error.html#variant.MissingTexture
+/// [`Error::BadTag`]: ./error/enum.Error.html#variant.BadTag
+/// [`Error::UnexpectedEof`]: ./error/enum.Error.html#variant.UnexpectedEof
+/// [`Error::ImageError`]: ./error/enum.Error.html#variant.ImageError
 pub fn process_sc(
     data: &[u8],
     file_name: &str,
     out_dir: &Path,
     png_dir: &Path,
-    parallelize: bool,
+    options: &ExportOptions,
+    observer: &dyn Observer,
 ) -> Result<(), Error> {
-    if !parallelize {
-        println!("\nProcessing `{}` image(s)...", file_name.green().bold());
-    }
-
-    let mut stream = Reader::new(Cursor::new(data.to_vec()));
-    let mut offset_shape = 0;
-    let mut offset_sheet = 0;
-
-    let shape_count = stream.read_uint16();
-    let _total_animations = stream.read_uint16();
-    let total_textures = stream.read_uint16();
-    let _text_field_count = stream.read_uint16();
-    let mut use_low_res = false;
-    let _matrix_count = stream.read_uint16();
-    let _color_transformation_count = stream.read_uint16();
-
-    let mut sheet_data = Vec::new();
-    for _ in 0..total_textures {
-        sheet_data.push(SheetItem::new(0, 0, 1));
-    }
-
-    let mut sprite_data = Vec::new();
-    for _ in 0..shape_count {
-        sprite_data.push(SpriteItem::new(0, 0, Vec::new()));
-    }
-
-    let sheet_image = Arc::new(Mutex::new(Vec::new()));
-
-    for x in 0..total_textures as usize {
-        let png_path = png_dir.join(format!("{}_tex{}.png", file_name, "_".repeat(x)));
+    let file = ScFile::parse(data)?;
+    let file_name = file_name.trim_end_matches(".sc");
+
+    let sheet_path = |variant: TextureVariant, x: usize| {
+        png_dir.join(format!(
+            "{}{}{}.{}",
+            file_name,
+            variant.suffix(),
+            "_".repeat(x),
+            options.format.extension()
+        ))
+    };
+    let variant = options.texture_variant.unwrap_or_else(|| {
+        [
+            TextureVariant::Default,
+            TextureVariant::HighRes,
+            TextureVariant::LowRes,
+        ]
+        .iter()
+        .copied()
+        .find(|v| sheet_path(*v, 0).exists())
+        .unwrap_or(TextureVariant::Default)
+    });
+
+    let mut sheets = Vec::new();
+    for x in 0..file.textures.len() {
+        let png_path = sheet_path(variant, x);
         if png_path.exists() {
-            let opened_image = match image::open(&png_path) {
-                Ok(i) => i,
-                Err(_) => {
-                    return Err(Error::IoError(format!(
-                        "{} {}",
-                        "Unable to open image".red(),
-                        png_path.to_str().unwrap().red()
-                    )))
-                }
-            };
+            let mut sheet = image::open(&png_path)?.to_rgba8();
+            if options.unpremultiply {
+                premultiply(&mut sheet);
+            }
 
-            sheet_image.lock().unwrap().push(opened_image);
+            sheets.push(sheet);
         } else {
-            return Err(Error::from(
-                format!(
-                    "Expected extracted png image `{}` for file",
-                    png_path.to_str().unwrap(),
-                )
-                .red()
-                .to_string()
-            ));
+            return Err(Error::MissingTexture { path: png_path });
         }
     }
 
-    // Read 500 bytes
-    stream.read(5);
+    let sprites = cut_shapes(&file, &sheets, observer)?;
+    let names = sprite_names(&file, &sprites, file_name, options);
 
-    let export_count = stream.read_uint16();
+    sprites
+        .par_iter()
+        .zip(names)
+        .try_for_each(|(sprite, name)| {
+            let name = match name {
+                Some(n) => out_dir.join(n),
+                None => return Ok(()),
+            };
+            let save_path = save_image(&sprite.image, &name.to_string_lossy(), options)?;
 
-    for _ in 0..export_count {
-        stream.read_uint16();
-    }
+            observer.on_event(&Event::SpriteWritten {
+                shape_id: sprite.shape_id,
+                path: &save_path,
+            });
 
-    for _ in 0..export_count {
-        let length = stream.read_byte() as usize;
-        stream.read_string(length);
+            Ok::<(), Error>(())
+        })?;
+
+    if options.movie_clips || options.animations {
+        save_movie_clips(&file, &sprites, out_dir, options, observer)?;
     }
 
-    while stream.len() > 0 {
-        let data_block_tag = hex::encode(stream.read(1));
-        let data_block_size = stream.read_uint32();
+    Ok(())
+}
 
-        if data_block_tag == "01" || data_block_tag == "18" {
-            let _pixel_type = stream.read_byte();
+/// Returns the file names of `sprites`, without an extension.
+///
+/// Sprites are named after the exports reaching their shapes. Sprites of
+/// other shapes are named according to `options.sprite_fallback`, and `None`
+/// is returned for the ones that should not be saved. Characters that are
+/// not valid in file names are replaced with `_`, and names used multiple
+/// times get the index of the sprite appended, followed by a counter if that
+/// name is used too.
+fn sprite_names(
+    file: &ScFile,
+    sprites: &[Sprite],
+    file_name: &str,
+    options: &ExportOptions,
+) -> Vec<Option<String>> {
+    let export_names = if options.numbered_sprites {
+        HashMap::new()
+    } else {
+        file.shape_names()
+    };
 
-            sheet_data[offset_sheet].x = stream.read_uint16().into();
-            sheet_data[offset_sheet].y = stream.read_uint16().into();
+    // Number of digits in the number.
+    let max_range = (sprites.len() as f64).log10().round() as usize + 1;
+
+    let mut used = HashSet::new();
+    sprites
+        .iter()
+        .enumerate()
+        .map(|(x, sprite)| {
+            let name = match export_names.get(&sprite.shape_id) {
+                Some(name) => sanitize_name(name),
+                None => match options.sprite_fallback {
+                    SpriteFallback::Index => {
+                        format!("{}_sprite_{:0>2$}", file_name, x, max_range)
+                    }
+                    SpriteFallback::ShapeId => format!("{}_shape_{}", file_name, sprite.shape_id),
+                    SpriteFallback::Skip => return None,
+                },
+            };
 
-            let image = &sheet_image.lock().unwrap()[offset_sheet];
+            let mut unique = name.clone();
+            let mut count = 0;
+            while !used.insert(unique.clone()) {
+                count += 1;
+                unique = if count == 1 {
+                    format!("{}_{}", name, x)
+                } else {
+                    format!("{}_{}_{}", name, x, count)
+                };
+            }
+
+            Some(unique)
+        })
+        .collect()
+}
 
-            if image.width() != sheet_data[offset_sheet].x
-                && image.height() != sheet_data[offset_sheet].y
-            {
-                use_low_res = true;
+/// Replaces the characters of `name` that are not valid in file names with `_`.
+fn sanitize_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
             }
-            offset_sheet += 1;
-        } else if data_block_tag == "1e" || data_block_tag == "1a" {
+        })
+        .collect()
+}
+
+/// Renders the exported movie clips of `file` and saves their frames and
+/// animations, as selected by `options`.
+///
+/// The output of every clip is saved in a directory named after its export.
+/// Frames are saved as `{export}_{frame}` and animations as `{export}`. If
+/// `options.frame_label` is set, the label follows the export name.
+fn save_movie_clips(
+    file: &ScFile,
+    sprites: &[Sprite],
+    out_dir: &Path,
+    options: &ExportOptions,
+    observer: &dyn Observer,
+) -> Result<(), Error> {
+    let mut used = HashSet::new();
+    for export in &file.exports {
+        if !file.movie_clips.iter().any(|c| c.id == export.id) {
             continue;
-        } else if data_block_tag == "12" {
-            // A polygon.
-            let i = if use_low_res { 2 } else { 1 };
-
-            sprite_data[offset_shape].id = stream.read_uint16().into();
-            sprite_data[offset_shape].total_regions = stream.read_uint16().into();
-            stream.read_uint16();
-
-            let mut regions = Vec::new();
-            for _ in 0..sprite_data[offset_shape].total_regions {
-                regions.push(Region {
-                    top: -32767,
-                    left: 32767,
-                    bottom: 32767,
-                    right: -32767,
-                    ..Default::default()
-                });
-            }
-            sprite_data[offset_shape].regions = regions;
+        }
 
-            for y in 0..sprite_data[offset_shape].total_regions as usize {
-                let data_block_tag_16 = hex::encode(stream.read(1));
+        let name = sanitize_name(&export.name);
+        if !used.insert(name.clone()) {
+            continue;
+        }
 
-                if data_block_tag_16 == "16" {
-                    let _data_block_size_16 = stream.read_uint32();
-                    sprite_data[offset_shape].regions[y].sheet_id = stream.read_byte().into();
-                    sprite_data[offset_shape].regions[y].num_points = stream.read_byte().into();
+        let mut animation =
+            render_movie_clip(file, sprites, export.id, !options.ignore_color_transforms)?;
+        let mut stem = name.clone();
+        if let Some(label) = &options.frame_label {
+            animation = match animation.sub_animation(label) {
+                Some(a) => a,
+                None => continue,
+            };
+            stem = format!("{}_{}", stem, sanitize_name(label));
+        }
 
-                    let mut shape_points = Vec::new();
-                    let mut sheet_points = Vec::new();
-                    for _ in 0..sprite_data[offset_shape].regions[y].num_points {
-                        shape_points.push(Point::new(0, 0));
-                        sheet_points.push(Point::new(0, 0));
-                    }
+        let clip_dir = out_dir.join(&name);
+        fs::create_dir_all(&clip_dir)?;
+
+        if options.movie_clips {
+            // Number of digits in the number.
+            let max_range = (animation.frames.len() as f64).log10().round() as usize + 1;
+
+            animation
+                .frames
+                .par_iter()
+                .enumerate()
+                .try_for_each(|(x, frame)| {
+                    let name = clip_dir.join(format!("{}_{:0>2$}", stem, x, max_range));
+                    let save_path = save_image(&frame.image, &name.to_string_lossy(), options)?;
+
+                    observer.on_event(&Event::FrameWritten {
+                        clip_id: export.id,
+                        frame: x,
+                        path: &save_path,
+                    });
+
+                    Ok::<(), Error>(())
+                })?;
+        }
 
-                    sprite_data[offset_shape].regions[y].shape_points = shape_points;
-                    sprite_data[offset_shape].regions[y].sheet_points = sheet_points;
+        if options.animations && !animation.frames.is_empty() {
+            let name = clip_dir.join(&stem);
+            let save_path = save_animation(&animation, &name.to_string_lossy(), options)?;
 
-                    for z in 0..sprite_data[offset_shape].regions[y].num_points as usize {
-                        sprite_data[offset_shape].regions[y].shape_points[z].x =
-                            stream.read_int32();
-                        sprite_data[offset_shape].regions[y].shape_points[z].y =
-                            stream.read_int32();
-                    }
-                    for z in 0..sprite_data[offset_shape].regions[y].num_points as usize {
-                        sprite_data[offset_shape].regions[y].sheet_points[z].x =
-                            ((stream.read_uint16() as f32
-                                * sheet_data[sprite_data[offset_shape].regions[y].sheet_id as usize]
-                                    .x as f32
-                                / 65535.0)
-                                .round()
-                                / (i as f32)) as i32;
-
-                        sprite_data[offset_shape].regions[y].sheet_points[z].y =
-                            ((stream.read_uint16() as f32
-                                * sheet_data[sprite_data[offset_shape].regions[y].sheet_id as usize]
-                                    .y as f32
-                                / 65535.0)
-                                .round()
-                                / (i as f32)) as i32;
-                    }
-                }
-            }
+            observer.on_event(&Event::AnimationWritten {
+                clip_id: export.id,
+                path: &save_path,
+            });
+        }
+    }
 
-            stream.read(5);
-            offset_shape += 1;
-            continue;
-        } else if data_block_tag == "08" {
-            // A matrix.
-            let mut points = Vec::new();
-            for _ in 0..6 {
-                points.push(stream.read_int32());
-            }
-            continue;
-        } else if data_block_tag == "0c" {
-            // An animation.
-            let _clip_id = stream.read_uint16();
-            let _clip_fps = stream.read_byte();
-            let _clip_frame_count = stream.read_uint16();
-
-            let cnt_1 = stream.read_int32();
-
-            for _ in 0..cnt_1 {
-                stream.read_uint16();
-                stream.read_uint16();
-                stream.read_uint16();
-            }
+    Ok(())
+}
 
-            let cnt_2 = stream.read_int16();
-            for _ in 0..cnt_2 {
-                stream.read_int16();
-            }
+/// Cuts sprites out of decoded texture sheets using compressed, raw `.sc`
+/// file data.
+///
+/// `sheets` must contain the images of the `_tex.sc` file corresponding to
+/// this `.sc` file, in the order they appear in it. They can be obtained
+/// with [`decode_tex`]. One sprite is returned for each shape, in the order
+/// the shapes appear in the file. Warnings are reported to `observer`.
+///
+/// Like the sheets, the sprites have premultiplied alpha. Regions of a shape
+/// are composited on top of each other accordingly.
+///
+/// The sheets can be of any [`TextureVariant`]. If their dimensions differ
+/// from the ones stored in the `.sc` file, coordinates are scaled by the
+/// ratio of them.
+///
+/// ## Errors
+///
+/// If fewer sheets than the textures of the file are passed,
+/// [`Error::MissingSheet`] is returned.
+///
+/// Parsing errors are returned as described in [`ScFile::parse`].
+///
+/// [`decode_tex`]: ./fn.decode_tex.html
+/// [`TextureVariant`]: ./enum.TextureVariant.html
+/// [`ScFile::parse`]: ./model/struct.ScFile.html#method.parse
+/// [`Error::MissingSheet`]: ./error/enum.Error.html#variant.MissingSheet
+pub fn cut_sprites(
+    data: &[u8],
+    sheets: &[RgbaImage],
+    observer: &dyn Observer,
+) -> Result<Vec<Sprite>, Error> {
+    cut_shapes(&ScFile::parse(data)?, sheets, observer)
+}
 
-            for _ in 0..cnt_2 {
-                stream.read_byte();
-            }
+/// Cuts the shapes of a parsed `.sc` file out of `sheets`.
+///
+/// It works like [`cut_sprites`], for a file parsed with [`ScFile::parse`].
+///
+/// [`cut_sprites`]: ./fn.cut_sprites.html
+/// [`ScFile::parse`]: ./model/struct.ScFile.html#method.parse
+pub fn cut_shapes(
+    file: &ScFile,
+    sheets: &[RgbaImage],
+    observer: &dyn Observer,
+) -> Result<Vec<Sprite>, Error> {
+    if file.textures.len() > sheets.len() {
+        return Err(Error::MissingSheet {
+            index: sheets.len(),
+        });
+    }
 
-            for _ in 0..cnt_2 {
-                let string_length = stream.read_byte() as usize;
-                if string_length < 255 {
-                    stream.read_string(string_length);
-                }
-            }
-        } else {
-            stream.read(data_block_size as usize);
+    let mut sprite_data = Vec::with_capacity(file.shapes.len());
+    for shape in &file.shapes {
+        let mut regions = Vec::with_capacity(shape.regions.len());
+        for region in &shape.regions {
+            let sheet_id = region.texture as usize;
+            let sheet = sheets
+                .get(sheet_id)
+                .ok_or(Error::MissingSheet { index: sheet_id })?;
+
+            // Sheets of another texture variant have a different resolution
+            // than the one stored in the `.sc` file, so points are scaled by
+            // the ratio of them.
+            let (width, height) = sheet.dimensions();
+            let (tag_width, tag_height) = file
+                .textures
+                .get(sheet_id)
+                .map_or((width, height), |t| (t.width, t.height));
+            let (scale_x, scale_y) = if tag_width > 0 && tag_height > 0 {
+                (
+                    width as f32 / tag_width as f32,
+                    height as f32 / tag_height as f32,
+                )
+            } else {
+                (1.0, 1.0)
+            };
+
+            regions.push(Region {
+                sheet_id: region.texture.into(),
+                num_points: region.points.len() as u32,
+                // Shapes are cut in integer twips, as stored in the file.
+                shape_points: region
+                    .points
+                    .iter()
+                    .map(|&(x, y)| {
+                        Point::new(
+                            (x * TWIPS_PER_PIXEL).round() as i32,
+                            (y * TWIPS_PER_PIXEL).round() as i32,
+                        )
+                    })
+                    .collect(),
+                sheet_points: region
+                    .texture_points(tag_width, tag_height)
+                    .into_iter()
+                    .map(|(x, y)| {
+                        Point::new((x * scale_x).round() as i32, (y * scale_y).round() as i32)
+                    })
+                    .collect(),
+                top: -32767,
+                left: 32767,
+                bottom: 32767,
+                right: -32767,
+                ..Default::default()
+            });
         }
+
+        sprite_data.push(SpriteItem::new(
+            shape.id.into(),
+            regions.len() as u32,
+            regions,
+        ));
     }
 
-    write_shape(
-        &mut sprite_data,
-        &mut sheet_data,
-        shape_count,
-        sheet_image,
-        &file_name,
-        &out_dir,
-    )
+    let shape_count = sprite_data.len() as u16;
+    Ok(write_shape(&mut sprite_data, shape_count, sheets, observer))
 }
 
-/// Writes shapes from the data on images.
+/// Cuts shapes from the data out of sheet images.
 fn write_shape(
-    sprite_data: &mut Vec<SpriteItem>,
-    sheet_data: &mut Vec<SheetItem>,
+    sprite_data: &mut [SpriteItem],
     shape_count: u16,
-    sheet_image: Arc<Mutex<Vec<image::DynamicImage>>>,
-    file_name: &str,
-    out_dir: &Path,
-) -> Result<(), Error> {
+    sheets: &[RgbaImage],
+    observer: &dyn Observer,
+) -> Vec<Sprite> {
     let mut max_left = 0;
     let mut max_right = 0;
     let mut max_above = 0;
@@ -435,8 +639,7 @@ fn write_shape(
 
             region_rotation(&mut sprite_item.regions[y]);
 
-            if sprite_item.regions[y].rotation == 90 || sprite_item.regions[y].rotation == 270
-            {
+            if sprite_item.regions[y].rotation == 90 || sprite_item.regions[y].rotation == 270 {
                 sprite_item.regions[y].sprite_width = (region_max_y - region_min_y) as u32;
                 sprite_item.regions[y].sprite_height = (region_max_x - region_min_x) as u32;
             } else {
@@ -500,94 +703,109 @@ fn write_shape(
     sprite_global.global_zero_x = max_left;
     sprite_global.global_zero_y = max_above;
 
-    // Number of digits in the number.
-    let max_range = (shape_count as f64).log10().round() as usize + 1;
-
-    (0..shape_count as usize).into_par_iter().try_for_each(|x| {
-        let out_image = Arc::new(Mutex::new(RgbaImage::new(
-            sprite_global.sprite_width,
-            sprite_global.sprite_height,
-        )));
+    (0..shape_count as usize)
+        .into_par_iter()
+        .map(|x| {
+            // Regions are cut in parallel, but composited in order as
+            // blending them depends on it.
+            let region_images: Vec<_> = (0..sprite_data[x].total_regions as usize)
+                .into_par_iter()
+                .map(|y| {
+                    let mut polygon = Vec::new();
+                    for z in 0..sprite_data[x].regions[y].num_points as usize {
+                        polygon.push(Point2D::new(
+                            sprite_data[x].regions[y].sheet_points[z].x,
+                            sprite_data[x].regions[y].sheet_points[z].y,
+                        ));
+                    }
 
-        (0..sprite_data[x].total_regions as usize)
-            .into_par_iter()
-            .for_each(|y| {
-                let mut polygon = Vec::new();
-                for z in 0..sprite_data[x].regions[y].num_points as usize {
-                    polygon.push(Point2D::new(
-                        sprite_data[x].regions[y].sheet_points[z].x,
-                        sprite_data[x].regions[y].sheet_points[z].y,
-                    ));
-                }
+                    if polygon[0] == polygon[polygon.len() - 1] {
+                        return None;
+                    }
 
-                if polygon[0] == polygon[polygon.len() - 1] {
-                    return;
-                }
+                    let sheet_id = sprite_data[x].regions[y].sheet_id as usize;
 
-                let sheet_id = sprite_data[x].regions[y].sheet_id as usize;
+                    let (width, height) = sheets[sheet_id].dimensions();
+                    let mut im_mask = GrayImage::new(width, height);
+                    draw_polygon_mut(&mut im_mask, polygon.as_slice(), image::Luma([255]));
 
-                let mut im_mask = GrayImage::new(sheet_data[sheet_id].x, sheet_data[sheet_id].y);
-                draw_convex_polygon_mut(&mut im_mask, polygon.as_slice(), image::Luma([255]));
+                    let bounds = get_bbox(&im_mask);
 
-                let bounds = get_bbox(&im_mask);
+                    let (temp_x, temp_y) = (bounds.2 - bounds.0, bounds.3 - bounds.1);
+                    im_mask =
+                        imageops::crop(&mut im_mask, bounds.0, bounds.1, temp_x, temp_y).to_image();
 
-                let (temp_x, temp_y) = (bounds.2 - bounds.0, bounds.3 - bounds.1);
-                im_mask =
-                    imageops::crop(&mut im_mask, bounds.0, bounds.1, temp_x, temp_y).to_image();
+                    let mut temp_region = RgbaImage::new(temp_x, temp_y);
+                    let copy_img =
+                        imageops::crop_imm(&sheets[sheet_id], bounds.0, bounds.1, temp_x, temp_y)
+                            .to_image();
 
-                let mut temp_region = RgbaImage::new(temp_x, temp_y);
-                let copy_img =
-                    sheet_image.lock().unwrap()[sheet_id].crop(bounds.0, bounds.1, temp_x, temp_y);
+                    // Overlay image content (`copy_img`) on `temp_region`, with `im_mask` as the mask.
+                    masked_overlay(&mut temp_region, &copy_img, 0, 0, &im_mask);
 
-                // Overlay image content (`copy_img`) on `temp_region`, with `im_mask` as the mask.
-                masked_overlay(&mut temp_region, &copy_img, 0, 0, &im_mask);
+                    // Mirror image if required.
+                    if sprite_data[x].regions[y].mirroring == 1 {
+                        imageops::flip_horizontal_in_place(&mut temp_region);
+                    }
 
-                // Mirror image if required.
-                if sprite_data[x].regions[y].mirroring == 1 {
-                    imageops::flip_horizontal_in_place(&mut temp_region);
+                    // Rotate image as appropriate.
+                    // Rotation is skipped if the angle is `0` or `360` to avoid
+                    // unnecessary processing.
+                    let angle = sprite_data[x].regions[y].rotation;
+                    let rotated_image = if angle != 0 && angle != 360 {
+                        rotate_uncropped(
+                            &temp_region,
+                            (angle as f32).to_radians(),
+                            Interpolation::Nearest,
+                            image::Rgba([0, 0, 0, 0]),
+                        )
+                    } else {
+                        temp_region
+                    };
+
+                    let paste_left =
+                        sprite_global.global_zero_x - sprite_data[x].regions[y].region_zero_x;
+                    let paste_top =
+                        sprite_global.global_zero_y - sprite_data[x].regions[y].region_zero_y;
+
+                    Some((rotated_image, paste_left, paste_top))
+                })
+                .collect();
+
+            let mut image = RgbaImage::new(sprite_global.sprite_width, sprite_global.sprite_height);
+            for (region_image, left, top) in region_images.into_iter().flatten() {
+                if let Err(e) = composite_over(&mut image, &region_image, left, top) {
+                    observer.on_event(&Event::Warning(&Error::ImageError(e)));
                 }
+            }
 
-                // Rotate image as appropriate.
-                // Rotation is skipped if the angle is `0` or `360` to avoid
-                // unnecessary processing.
-                let angle = sprite_data[x].regions[y].rotation;
-                let rotated_image = if angle != 0 && angle != 360 {
-                    rotate_uncropped(
-                        &temp_region,
-                        (angle as f32).to_radians(),
-                        Interpolation::Nearest,
-                        image::Rgba([0, 0, 0, 0]),
+            // Regions are pasted with their own scale, so the one of the first
+            // region with an area is used for the whole sprite. Shape points
+            // are in twips here, so it is converted to pixels of the shape.
+            let scale = sprite_data[x]
+                .regions
+                .iter()
+                .find(|r| {
+                    r.right > r.left
+                        && r.top > r.bottom
+                        && r.sprite_width > 0
+                        && r.sprite_height > 0
+                })
+                .map_or((1.0, 1.0), |r| {
+                    (
+                        r.sprite_width as f32 * TWIPS_PER_PIXEL / (r.right - r.left) as f32,
+                        r.sprite_height as f32 * TWIPS_PER_PIXEL / (r.top - r.bottom) as f32,
                     )
-                } else {
-                    temp_region
-                };
-
-                let paste_left =
-                    sprite_global.global_zero_x - sprite_data[x].regions[y].region_zero_x;
-                let paste_top =
-                    sprite_global.global_zero_y - sprite_data[x].regions[y].region_zero_y;
-
-                if out_image
-                    .lock()
-                    .unwrap()
-                    .copy_from(&rotated_image, paste_left, paste_top)
-                    .is_err()
-                {
-                    println!(
-                        "{}",
-                        "There was an error processing a portion of the image.".red()
-                    );
-                }
-            });
-
-        let save_path = out_dir.join(format!("{}_sprite_{:0>2$}.png", file_name, x, max_range));
-
-        if out_image.lock().unwrap().save(save_path).is_err() {
-            return Err(Error::IoError(format!("{}", "Unable to save image.".red())));
-        }
+                });
 
-        Ok(())
-    })
+            Sprite {
+                shape_id: sprite_data[x].id as u16,
+                image,
+                origin: (sprite_global.global_zero_x, sprite_global.global_zero_y),
+                scale,
+            }
+        })
+        .collect()
 }
 
 /// Returns bounding box of the image.
@@ -793,14 +1011,19 @@ where
     new_image
 }
 
+/// Composites a premultiplied pixel over another one.
+fn blend_over(bottom: &mut Rgba<u8>, top: &Rgba<u8>) {
+    let inverse = 255 - top[3] as u32;
+
+    for c in 0..4 {
+        bottom[c] = (top[c] as u32 + (bottom[c] as u32 * inverse + 127) / 255).min(255) as u8;
+    }
+}
+
 /// Overlay an image at a given coordinate (x, y) if the point is not transparent on the mask.
-/// The mask must have the same dimensions as `bottom`.
-fn masked_overlay<I, J, K>(bottom: &mut I, top: &J, x: u32, y: u32, mask: &K)
-where
-    I: GenericImage,
-    J: GenericImageView<Pixel = I::Pixel>,
-    K: GenericImageView<Pixel = image::Luma<u8>>,
-{
+/// The mask must have the same dimensions as `bottom`. Both images must have
+/// premultiplied alpha.
+fn masked_overlay(bottom: &mut RgbaImage, top: &RgbaImage, x: u32, y: u32, mask: &GrayImage) {
     let bottom_dims = bottom.dimensions();
     let top_dims = top.dimensions();
 
@@ -813,9 +1036,33 @@ where
                 continue;
             }
 
-            bottom
-                .get_pixel_mut(x + top_x, y + top_y)
-                .blend(&top.get_pixel(top_x, top_y));
+            blend_over(
+                bottom.get_pixel_mut(x + top_x, y + top_y),
+                top.get_pixel(top_x, top_y),
+            );
         }
     }
 }
+
+/// Composites `top` over `bottom` at the given coordinate (x, y).
+///
+/// Both images must have premultiplied alpha. If `top` does not fit inside
+/// `bottom`, an error is returned and `bottom` is left unchanged.
+pub(crate) fn composite_over(
+    bottom: &mut RgbaImage,
+    top: &RgbaImage,
+    x: u32,
+    y: u32,
+) -> Result<(), ImageError> {
+    if bottom.width() < top.width() + x || bottom.height() < top.height() + y {
+        return Err(ImageError::Parameter(ParameterError::from_kind(
+            ParameterErrorKind::DimensionMismatch,
+        )));
+    }
+
+    for (top_x, top_y, pixel) in top.enumerate_pixels() {
+        blend_over(bottom.get_pixel_mut(x + top_x, y + top_y), pixel);
+    }
+
+    Ok(())
+}
@@ -1,204 +1,598 @@
 use crate::{
+    codecs::ktx,
     error::Error,
-    utils::{decompress, Reader},
+    export::{save_image, ExportOptions},
+    observer::{Event, Observer},
+    utils::{decompress, decompress_zstd, Reader},
 };
-use colored::Colorize;
-use image::{Rgba, RgbaImage};
-use std::path::Path;
+use image::RgbaImage;
+use rayon::prelude::*;
+use std::{fs, path::Path};
 
-/// Reads some data from the stream and returns appropriate pixel data.
+/// Expands a channel of `bits` bits, where `4 <= bits <= 8`, to 8 bits.
 ///
-/// The bitwise transformations depend on the type of the pixel. One of the following
-/// types is valid: `0, 1, 2, 3, 4, 6, 10`.
+/// The high bits are repeated in the low bits, so that the maximum value of
+/// the channel becomes 255.
+fn expand(value: u16, bits: u32) -> u8 {
+    ((value << (8 - bits)) | (value >> (2 * bits - 8))) as u8
+}
+
+/// Side length of the square tiles pixels of tiled textures are stored in.
+const TILE_SIZE: usize = 32;
+
+/// Returns the number of bytes of an uncompressed pixel type.
 ///
-/// If `pixel_type` is not one of the above, `UnknownPixel` is raised. Otherwise, an array
-/// of four `u8`s is returned, wrapped around by `Ok`.
+/// One of the following types is valid: `0, 1, 2, 3, 4, 6, 10`. For any
+/// other type, `None` is returned. Block-compressed textures are stored in
+/// KTX containers instead.
+fn pixel_size(pixel_type: u8) -> Option<usize> {
+    match pixel_type {
+        0 | 1 => Some(4),
+        2 | 3 | 4 | 6 => Some(2),
+        10 => Some(1),
+        _ => None,
+    }
+}
+
+/// Converts a single pixel to RGBA.
 ///
-/// ## Arguments
+/// The bitwise transformations depend on the type of the pixel. `pixel` holds
+/// exactly [`pixel_size`] bytes of a valid pixel type. Channels narrower than
+/// 8 bits are expanded to the full range, like the GPU does when sampling them.
 ///
-/// * `reader`: `Reader` representing the data stream.
-/// * `pixel_type`: The type of pixel. For `_tex.sc` data, it is the image sub-type.
-fn convert_pixel(reader: &mut Reader, pixel_type: u8) -> Result<[u8; 4], Error> {
+/// [`pixel_size`]: ./fn.pixel_size.html
+fn convert_pixel(pixel: &[u8], pixel_type: u8) -> [u8; 4] {
+    let word = || u16::from_le_bytes([pixel[0], pixel[1]]);
+
     match pixel_type {
-        // RGB8888
-        0 | 1 => {
-            let pixel = reader.read(4);
-            Ok([pixel[0], pixel[1], pixel[2], pixel[3]])
-        }
         // RGB4444
         2 => {
-            let pixel = reader.read_uint16();
-            Ok([
-                (((pixel >> 12) & 0xF) << 4) as u8,
-                (((pixel >> 8) & 0xF) << 4) as u8,
-                (((pixel >> 4) & 0xF) << 4) as u8,
-                ((pixel & 0xF) << 4) as u8,
-            ])
+            let pixel = word();
+            [
+                expand((pixel >> 12) & 0xF, 4),
+                expand((pixel >> 8) & 0xF, 4),
+                expand((pixel >> 4) & 0xF, 4),
+                expand(pixel & 0xF, 4),
+            ]
         }
         // RGBA5551
         3 => {
-            let pixel = reader.read_uint16();
-            Ok([
-                (((pixel >> 11) & 0x1F) << 3) as u8,
-                (((pixel >> 6) & 0x1F) << 3) as u8,
-                (((pixel >> 1) & 0x1F) << 3) as u8,
-                ((pixel & 0xFF) << 7) as u8,
-            ])
+            let pixel = word();
+            [
+                expand((pixel >> 11) & 0x1F, 5),
+                expand((pixel >> 6) & 0x1F, 5),
+                expand((pixel >> 1) & 0x1F, 5),
+                if pixel & 1 == 1 { 255 } else { 0 },
+            ]
         }
         // RGB565
         4 => {
-            let pixel = reader.read_uint16();
-            Ok([
-                (((pixel >> 11) & 0x1F) << 3) as u8,
-                (((pixel >> 5) & 0x3F) << 2) as u8,
-                ((pixel & 0x1F) << 3) as u8,
+            let pixel = word();
+            [
+                expand((pixel >> 11) & 0x1F, 5),
+                expand((pixel >> 5) & 0x3F, 6),
+                expand(pixel & 0x1F, 5),
                 // Alpha channel must always be 255 for type 4.
                 255,
-            ])
+            ]
         }
         // LA88
         6 => {
-            let pixel = reader.read_uint16();
-            Ok([
+            let pixel = word();
+            [
                 (pixel >> 8) as u8,
                 (pixel >> 8) as u8,
                 (pixel >> 8) as u8,
                 (pixel & 0xFF) as u8,
-            ])
+            ]
         }
-        10 => {
-            let pixel = reader.read_byte();
-            Ok([pixel; 4])
-        }
-        _ => Err(Error::UnknownPixel(format!(
-            "Unknown pixel type ({}).",
-            pixel_type
-        ))),
+        // L8, which is fully opaque.
+        10 => [pixel[0], pixel[0], pixel[0], 255],
+        // RGB8888
+        _ => [pixel[0], pixel[1], pixel[2], pixel[3]],
     }
 }
 
-/// Adjusts some pixels.
-fn adjust_pixels(img: &mut RgbaImage, pixels: Vec<[u8; 4]>, height: u32, width: u32) {
-    let mut i = 0;
-    let block_size = 32;
-    let h_limit = (height as f64 / block_size as f64).ceil() as u32;
-    let w_limit = (width as f64 / block_size as f64).ceil() as u32;
-
-    for _h in 0..h_limit {
-        for _w in 0..w_limit {
-            let mut h = _h * block_size;
-            while h != (_h + 1) * block_size && h < height as u32 {
-                let mut w = _w * block_size;
-                while w != (_w + 1) * block_size && w < width as u32 {
-                    img.put_pixel(
-                        w,
-                        h,
-                        Rgba([pixels[i][0], pixels[i][1], pixels[i][2], pixels[i][3]]),
-                    );
-                    i += 1;
-                    w += 1;
+/// Converts a run of consecutive pixels into RGBA bytes of `dst`.
+fn convert_row(src: &[u8], dst: &mut [u8], pixel_type: u8, pixel_size: usize) {
+    if pixel_size == 4 {
+        // RGBA8888 is stored as is.
+        dst.copy_from_slice(src);
+        return;
+    }
+
+    for (pixel, out) in src.chunks_exact(pixel_size).zip(dst.chunks_exact_mut(4)) {
+        out.copy_from_slice(&convert_pixel(pixel, pixel_type));
+    }
+}
+
+/// Decodes uncompressed pixel data into an image.
+///
+/// Every row of the image is converted in parallel, straight from `data` into
+/// the image buffer. If `tiled` is `true`, pixels are stored in tiles of 32x32
+/// instead of row by row. Tiles are stored row by row, and so are the pixels
+/// within a tile. Tiles on the right and bottom edges are cropped to the image.
+///
+/// `data` must hold exactly `width * height` pixels of `pixel_type`.
+fn decode_pixels(data: &[u8], pixel_type: u8, width: u32, height: u32, tiled: bool) -> RgbaImage {
+    let pixel_size = pixel_size(pixel_type).expect("pixel type is uncompressed");
+    let (width, height) = (width as usize, height as usize);
+    let row_size = width * 4;
+
+    let mut buf = vec![0; row_size * height];
+    if row_size > 0 {
+        buf.par_chunks_mut(row_size)
+            .enumerate()
+            .for_each(|(y, row)| {
+                if !tiled {
+                    let start = y * width * pixel_size;
+                    let src = &data[start..start + width * pixel_size];
+                    convert_row(src, row, pixel_type, pixel_size);
+                    return;
                 }
-                h += 1;
-            }
+
+                let tile_top = y / TILE_SIZE * TILE_SIZE;
+                let tile_height = TILE_SIZE.min(height - tile_top);
+                for x in (0..width).step_by(TILE_SIZE) {
+                    let tile_width = TILE_SIZE.min(width - x);
+                    // All tiles above are full height and all tiles to the
+                    // left are full width.
+                    let start = tile_top * width + x * tile_height + (y - tile_top) * tile_width;
+
+                    let src = &data[start * pixel_size..(start + tile_width) * pixel_size];
+                    let dst = &mut row[x * 4..(x + tile_width) * 4];
+                    convert_row(src, dst, pixel_type, pixel_size);
+                }
+            });
+    }
+
+    RgbaImage::from_raw(width as u32, height as u32, buf).expect("buffer fits the image")
+}
+
+/// Moves `reader` to `tag_end`, the end of the tag `tag` at `tag_offset`.
+///
+/// If the tag was read past its end, `BadTag` is returned.
+fn seek_tag_end(
+    reader: &mut Reader,
+    tag: u8,
+    tag_offset: usize,
+    tag_end: usize,
+) -> Result<(), Error> {
+    if reader.offset() > tag_end {
+        return Err(Error::BadTag {
+            tag,
+            offset: tag_offset,
+        });
+    }
+
+    reader.seek(tag_end)
+}
+
+/// Reads and decompresses an external `.zktx` texture file.
+///
+/// `name` is resolved relative to `tex_dir`. If `tex_dir` is `None` or the file
+/// does not exist, `MissingTexture` is raised.
+fn read_zktx(name: &str, tex_dir: Option<&Path>) -> Result<Vec<u8>, Error> {
+    let path = match tex_dir {
+        Some(dir) => dir.join(name),
+        None => return Err(Error::MissingTexture { path: name.into() }),
+    };
+
+    if !path.exists() {
+        return Err(Error::MissingTexture { path });
+    }
+
+    Ok(decompress_zstd(&fs::read(&path)?)?.into_inner())
+}
+
+/// Filtering the game uses when sampling a texture.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Filter {
+    /// Linear filtering, without mipmaps.
+    Linear,
+    /// Linear filtering within the nearest mip level.
+    LinearMipmap,
+    /// Nearest-neighbour filtering.
+    Nearest,
+}
+
+/// A texture tag of `_tex.sc` files.
+///
+/// All tags start with the pixel sub-type and the dimensions of the texture.
+/// They differ in how the pixels are stored and sampled.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TextureTag {
+    /// Tag `1`, pixels stored row by row.
+    Texture,
+    /// Tag `16`, pixels stored row by row and sampled with mipmaps.
+    Texture2,
+    /// Tag `19`, pixels stored row by row and sampled with mipmaps.
+    Texture3,
+    /// Tag `24`, pixels stored row by row.
+    Texture4,
+    /// Tag `27`, pixels stored in tiles of 32x32.
+    Texture5,
+    /// Tag `28`, pixels stored in tiles of 32x32.
+    Texture6,
+    /// Tag `29`, pixels stored in tiles of 32x32 and sampled with mipmaps.
+    Texture7,
+    /// Tag `34`, pixels stored row by row and sampled without interpolation.
+    Texture8,
+    /// Tag `45`, pixels stored in an embedded KTX container.
+    Texture9,
+    /// Tag `47`, pixels stored in an external, zstd-compressed `.zktx` file.
+    Texture10,
+}
+
+impl TextureTag {
+    /// Returns the texture tag with the given value, if it is a texture tag.
+    pub fn from_tag(tag: u8) -> Option<Self> {
+        match tag {
+            1 => Some(Self::Texture),
+            16 => Some(Self::Texture2),
+            19 => Some(Self::Texture3),
+            24 => Some(Self::Texture4),
+            27 => Some(Self::Texture5),
+            28 => Some(Self::Texture6),
+            29 => Some(Self::Texture7),
+            34 => Some(Self::Texture8),
+            45 => Some(Self::Texture9),
+            47 => Some(Self::Texture10),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of the tag.
+    pub fn tag(self) -> u8 {
+        match self {
+            Self::Texture => 1,
+            Self::Texture2 => 16,
+            Self::Texture3 => 19,
+            Self::Texture4 => 24,
+            Self::Texture5 => 27,
+            Self::Texture6 => 28,
+            Self::Texture7 => 29,
+            Self::Texture8 => 34,
+            Self::Texture9 => 45,
+            Self::Texture10 => 47,
+        }
+    }
+
+    /// Returns the filtering the game uses for textures with this tag.
+    pub fn filter(self) -> Filter {
+        match self {
+            Self::Texture2 | Self::Texture3 | Self::Texture7 => Filter::LinearMipmap,
+            Self::Texture8 => Filter::Nearest,
+            _ => Filter::Linear,
         }
     }
+
+    /// Returns `true` if pixels are stored in tiles of 32x32 instead of
+    /// row by row.
+    pub fn is_tiled(self) -> bool {
+        matches!(self, Self::Texture5 | Self::Texture6 | Self::Texture7)
+    }
+}
+
+/// A texture decoded from `_tex.sc` file data.
+#[derive(Clone, Debug)]
+pub struct Texture {
+    /// Tag of the texture block.
+    pub tag: TextureTag,
+    /// Pixel format of the texture. It is the image sub-type of the block.
+    pub pixel_format: u8,
+    /// Filtering the game uses when sampling the texture.
+    pub filter: Filter,
+    /// Width of the texture, in pixels.
+    pub width: u32,
+    /// Height of the texture, in pixels.
+    pub height: u32,
+    /// The decoded image.
+    pub image: RgbaImage,
+    /// Mip levels stored after the image, from the largest to the smallest.
+    ///
+    /// Only KTX textures store mip levels. The game generates them for other
+    /// textures sampled with mipmaps.
+    pub mipmaps: Vec<RgbaImage>,
+}
+
+/// Decodes compressed, raw `_tex.sc` file data into textures.
+///
+/// A single `_tex.sc` file can contain data for multiple sprites. All of the
+/// sprites are decoded and returned in the order they appear in the file.
+/// Uncompressed pixel types are supported, as well as ETC1, ETC2 and ASTC
+/// LDR blocks in KTX containers, embedded or external.
+/// Textures stored in external `.zktx` files are read from `tex_dir`, which
+/// is usually the directory of the `_tex.sc` file. Rows of pixels are decoded
+/// in parallel, straight into the image buffer of every texture.
+/// Textures with unknown pixel types are skipped and reported to `observer`
+/// as [`Event::Warning`].
+///
+/// ## Errors
+///
+/// If decompression is unsuccessful, [`Error::DecompressionError`] is returned.
+///
+/// If the decompressed data is truncated, [`Error::UnexpectedEof`] is returned.
+/// If a texture holds more data than the size of its tag, [`Error::BadTag`]
+/// is returned.
+///
+/// If an external texture file does not exist or `tex_dir` is `None`,
+/// [`Error::MissingTexture`] is returned. Invalid KTX data returns
+/// [`Error::KtxError`].
+///
+/// [`Event::Warning`]: ./enum.Event.html#variant.Warning
+/// [`Error::DecompressionError`]: ./error/enum.Error.html#variant.DecompressionError
+/// [`Error::UnexpectedEof`]: ./error/enum.Error.html#variant.UnexpectedEof
+/// [`Error::BadTag`]: ./error/enum.Error.html#variant.BadTag
+/// [`Error::MissingTexture`]: ./error/enum.Error.html#variant.MissingTexture
+/// [`Error::KtxError`]: ./error/enum.Error.html#variant.KtxError
+pub fn decode_tex(
+    data: &[u8],
+    tex_dir: Option<&Path>,
+    observer: &dyn Observer,
+) -> Result<Vec<Texture>, Error> {
+    let (_, decompressed) = decompress(data)?;
+
+    let mut reader = Reader::new(decompressed);
+
+    let mut textures = Vec::new();
+
+    while reader.len() > 0 {
+        let tag_offset = reader.offset();
+        let file_type = reader.read_byte()?;
+        let file_size = reader.read_uint32()?;
+        let tag_end = reader.offset() + file_size as usize;
+
+        let tag = match TextureTag::from_tag(file_type) {
+            Some(t) => t,
+            None => {
+                reader.skip(file_size as usize)?;
+                continue;
+            }
+        };
+
+        let sub_type = reader.read_byte()?;
+        let width = reader.read_uint16()? as u32;
+        let height = reader.read_uint16()? as u32;
+
+        observer.on_event(&Event::TextureStarted {
+            tag: file_type,
+            pixel_format: sub_type,
+            width,
+            height,
+        });
+
+        let mut mipmaps = Vec::new();
+        let img = if tag == TextureTag::Texture9 || tag == TextureTag::Texture10 {
+            let external;
+            let ktx_data = if tag == TextureTag::Texture9 {
+                let length = reader.read_uint32()? as usize;
+                reader.read_slice(length)?
+            } else {
+                let length = reader.read_byte()? as usize;
+                external = read_zktx(&reader.read_string(length)?, tex_dir)?;
+                external.as_slice()
+            };
+
+            let ktx = ktx::parse(ktx_data)?;
+            for level in 1..ktx.levels.len() {
+                let (width, height) = ktx.level_size(level);
+                mipmaps.push(ktx.format.decode(ktx.levels[level], width, height));
+            }
+
+            ktx.format.decode(ktx.levels[0], ktx.width, ktx.height)
+        } else {
+            let size = match pixel_size(sub_type) {
+                Some(size) => width as usize * height as usize * size,
+                None => {
+                    observer.on_event(&Event::Warning(&Error::UnknownPixel {
+                        tag: file_type,
+                        pixel_type: sub_type,
+                        offset: reader.offset(),
+                    }));
+
+                    // Skip the rest of the texture.
+                    seek_tag_end(&mut reader, file_type, tag_offset, tag_end)?;
+                    continue;
+                }
+            };
+
+            let data = reader.read_slice(size)?;
+            decode_pixels(data, sub_type, width, height, tag.is_tiled())
+        };
+
+        // Skip whatever the tag holds after the decoded image.
+        seek_tag_end(&mut reader, file_type, tag_offset, tag_end)?;
+
+        let (width, height) = img.dimensions();
+        observer.on_event(&Event::TextureDecoded {
+            index: textures.len(),
+            width,
+            height,
+        });
+
+        textures.push(Texture {
+            tag,
+            pixel_format: sub_type,
+            filter: tag.filter(),
+            width,
+            height,
+            image: img,
+            mipmaps,
+        });
+    }
+
+    Ok(textures)
 }
 
 /// Processes compressed, raw `_tex.sc` file data.
 ///
-/// If decompressing and pixel conversion is successful, the resultant png
-/// image is saved in the output directory (`out_dir`).
+/// The data is decoded with [`decode_tex`] and the resultant images are saved
+/// in the output directory (`out_dir`), in the format of `options.format`. External `.zktx` textures are
+/// resolved relative to `tex_dir`, the directory of the `_tex.sc` file.
 ///
 /// A single `_tex.sc` file can contain data for multiple sprites. All of the
 /// sprites are extracted and saved by this process. `_`s are appended to the
-/// file name in cases of multiple sprites.
+/// file name in cases of multiple sprites. If `options.mipmaps` is set, stored
+/// mip levels are saved as well, with `_mipN` appended to the name of the
+/// texture. Images are saved with premultiplied alpha unless
+/// `options.unpremultiply` is set.
 ///
-/// `parallelize` tells if the directory files are processed parallelly. It is
-/// simply used to control the stdout output.
+/// Progress and warnings are reported to `observer`.
 ///
 /// ## Errors
 ///
 /// If decompression is unsuccessful, [`Error::DecompressionError`] is returned.
-/// Pixel conversion errors are handled in the function itself.
+/// Unknown pixel types are handled in the function itself.
+///
+/// If the decompressed data is truncated, [`Error::UnexpectedEof`] is returned.
+/// If a texture holds more data than the size of its tag, [`Error::BadTag`]
+/// is returned.
 ///
-/// [`Error::IoError`] is returned if an IO operation fails.
+/// External texture and KTX errors are returned as described in [`decode_tex`].
 ///
+/// [`Error::ImageError`] is returned if saving an image fails.
+///
+/// [`decode_tex`]: ./fn.decode_tex.html
 /// [`Error::DecompressionError`]: ./error/enum.Error.html#variant.DecompressionError
-/// [`Error::IoError`]: ./error/enum.Error.html#variant.IoError
+/// [`Error::UnexpectedEof`]: ./error/enum.Error.html#variant.UnexpectedEof
+/// [`Error::BadTag`]: ./error/enum.Error.html#variant.BadTag
+/// [`Error::ImageError`]: ./error/enum.Error.html#variant.ImageError
 pub fn process_tex(
     data: &[u8],
     file_name: &str,
     out_dir: &Path,
-    parallelize: bool,
+    tex_dir: &Path,
+    options: &ExportOptions,
+    observer: &dyn Observer,
 ) -> Result<(), Error> {
-    if data.len() < 35 {
-        return Err(Error::DecompressionError(
-            "Size of file is too small".to_string(),
-        ));
+    let initial_path = out_dir.join(file_name.replace(".sc", ""));
+    for (pic_count, texture) in decode_tex(data, Some(tex_dir), observer)?
+        .iter()
+        .enumerate()
+    {
+        let name = format!("{}{}", initial_path.display(), "_".repeat(pic_count));
+        save_image(&texture.image, &name, options)?;
+
+        if options.mipmaps {
+            for (level, mipmap) in texture.mipmaps.iter().enumerate() {
+                save_image(mipmap, &format!("{}_mip{}", name, level + 1), options)?;
+            }
+        }
     }
 
-    let decompressed = decompress(&data[26..])?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let mut reader = Reader::new(decompressed);
+    /// Converts every little-endian word of `words` with `pixel_type`.
+    fn convert_words(pixel_type: u8, words: &[u16]) -> Vec<[u8; 4]> {
+        words
+            .iter()
+            .map(|w| convert_pixel(&w.to_le_bytes(), pixel_type))
+            .collect()
+    }
 
-    let mut pic_count = 0;
-    let possible_types = [1, 24, 27, 28];
+    #[test]
+    fn test_rgba8888() {
+        let data = [1, 2, 3, 4, 250, 0, 128, 255];
 
-    if !parallelize {
-        println!("\nExtracting {} image(s)...", file_name);
+        assert_eq!([1, 2, 3, 4], convert_pixel(&data[..4], 0));
+        assert_eq!([250, 0, 128, 255], convert_pixel(&data[4..], 1));
     }
 
-    'main: while reader.len() > 0 {
-        let file_type = reader.read_byte();
-        let file_size = reader.read_uint32();
+    #[test]
+    fn test_rgba4444() {
+        assert_eq!(
+            vec![
+                [255, 255, 255, 255],
+                [0, 0, 0, 0],
+                [255, 0, 0, 0],
+                [0, 0, 0, 255],
+                [17, 136, 170, 119],
+            ],
+            convert_words(2, &[0xFFFF, 0x0000, 0xF000, 0x000F, 0x18A7])
+        );
+    }
 
-        if !possible_types.contains(&file_type) {
-            reader.read(file_size as usize);
-            continue;
-        }
+    #[test]
+    fn test_rgba5551() {
+        assert_eq!(
+            vec![
+                [255, 255, 255, 255],
+                [0, 0, 0, 0],
+                [255, 0, 0, 0],
+                [0, 255, 0, 0],
+                [0, 0, 255, 0],
+                [0, 0, 0, 255],
+                [132, 66, 8, 255],
+            ],
+            convert_words(3, &[0xFFFF, 0x0000, 0xF800, 0x07C0, 0x003E, 0x0001, 0x8203])
+        );
+    }
 
-        let sub_type = reader.read_byte();
-        let width = reader.read_uint16() as u32;
-        let height = reader.read_uint16() as u32;
-
-        println!(
-            "file_type: {}, file_size: {}, sub_type: {}, width: {}, height: {}",
-            file_type.to_string().cyan().bold(),
-            file_size.to_string().cyan().bold(),
-            sub_type.to_string().cyan().bold(),
-            width.to_string().cyan().bold(),
-            height.to_string().cyan().bold()
+    #[test]
+    fn test_rgb565() {
+        assert_eq!(
+            vec![
+                [255, 255, 255, 255],
+                [0, 0, 0, 255],
+                [255, 0, 0, 255],
+                [0, 255, 0, 255],
+                [0, 0, 255, 255],
+                [132, 130, 8, 255],
+            ],
+            convert_words(4, &[0xFFFF, 0x0000, 0xF800, 0x07E0, 0x001F, 0x8401])
         );
+    }
 
-        let mut pixels = Vec::new();
-        let mut img = RgbaImage::new(width, height);
-        for y in 0..height {
-            for x in 0..width {
-                let pixel_data = match convert_pixel(&mut reader, sub_type) {
-                    Ok(v) => v,
-                    Err(e) => {
-                        println!("Error: {}", e.inner().red());
-                        continue 'main;
-                    }
-                };
-                pixels.push(pixel_data);
-
-                img.put_pixel(x, y, Rgba(pixel_data));
-            }
+    #[test]
+    fn test_la88() {
+        assert_eq!(
+            vec![[255, 255, 255, 255], [0, 0, 0, 0], [18, 18, 18, 52]],
+            convert_words(6, &[0xFFFF, 0x0000, 0x1234])
+        );
+    }
+
+    #[test]
+    fn test_l8() {
+        for l in &[0, 128, 255] {
+            assert_eq!([*l, *l, *l, 255], convert_pixel(&[*l], 10));
         }
+    }
 
-        if file_type == 27 || file_type == 28 {
-            adjust_pixels(&mut img, pixels, height, width);
+    #[test]
+    fn test_unknown() {
+        for pixel_type in &[5, 7, 8, 9, 11, 255] {
+            assert_eq!(None, pixel_size(*pixel_type));
         }
+    }
 
-        let initial_path = out_dir.join(file_name.replace(".sc", ""));
-        let path = format!("{}{}.png", initial_path.display(), "_".repeat(pic_count));
-        if img.save(path).is_err() {
-            return Err(Error::IoError("Failed to save image!".red().to_string()));
+    #[test]
+    fn test_tiled() {
+        // A 33x33 L8 image holds a full tile, two cropped tiles on the edges
+        // and a single pixel in the corner, in that order.
+        let (width, height) = (33, 33);
+        let mut data = Vec::new();
+        for (x0, y0, w, h) in &[
+            (0, 0, 32, 32),
+            (32, 0, 1, 32),
+            (0, 32, 32, 1),
+            (32, 32, 1, 1),
+        ] {
+            for y in *y0..y0 + h {
+                for x in *x0..x0 + w {
+                    data.push((y * width + x) as u8);
+                }
+            }
         }
 
-        pic_count += 1;
+        let image = decode_pixels(&data, 10, width, height, true);
+        for (x, y, pixel) in image.enumerate_pixels() {
+            assert_eq!((y * width + x) as u8, pixel[0]);
+        }
     }
-
-    Ok(())
 }
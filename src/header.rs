@@ -0,0 +1,199 @@
+use crate::{error::Error, utils::decompress};
+use std::convert::TryInto;
+
+/// Magic bytes at the start of every Supercell container.
+const SC_MAGIC: &[u8] = b"SC";
+
+/// Magic bytes at the start of a Zstandard frame.
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// Marker of the metadata block appended to version 4 containers.
+const METADATA_MARKER: &[u8] = b"START";
+
+/// Compression algorithm used for the payload of a file.
+///
+/// LZHAM compressed payloads, starting with `SCLZ`, are not supported.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Compression {
+    /// LZMA compression, used by `.csv` files and older `.sc` files.
+    Lzma,
+    /// Zstandard compression, used by version 3 and newer files.
+    Zstd,
+}
+
+impl Compression {
+    /// Detects the compression algorithm from the first bytes of a payload.
+    ///
+    /// `None` is returned if the compression algorithm is not known.
+    pub fn detect(payload: &[u8]) -> Option<Self> {
+        if payload.starts_with(ZSTD_MAGIC) {
+            return Some(Self::Zstd);
+        }
+
+        // The first byte of an LZMA stream encodes the `lc`, `lp` and `pb`
+        // properties, which can never exceed `224`. It is followed by the
+        // little-endian dictionary size.
+        if payload.len() >= 5 && payload[0] < 225 {
+            Some(Self::Lzma)
+        } else {
+            None
+        }
+    }
+}
+
+/// Header of a Supercell file.
+///
+/// `_tex.sc` and `.sc` files start with an `SC` magic, followed by a
+/// big-endian version and a hash of the content. `.csv` files usually
+/// have no such header and the compressed payload starts right away.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Header {
+    /// Version of the container. It is `None` if the file has no `SC` header.
+    pub version: Option<u32>,
+    /// Hash of the file content stored in the header. It is empty if the
+    /// file has no `SC` header.
+    pub hash: Vec<u8>,
+    /// Compression algorithm used for the payload.
+    pub compression: Compression,
+    /// Offset of the compressed payload from the start of the file.
+    pub payload_offset: usize,
+    /// Length of the compressed payload, in bytes.
+    pub payload_len: usize,
+}
+
+impl Header {
+    /// Parses the header of raw, compressed file data.
+    ///
+    /// Versions `1` to `3` store the hash right after the version. Version
+    /// `4` containers repeat the version before the hash and carry a
+    /// metadata block after the payload, which is excluded from
+    /// [`payload_len`].
+    ///
+    /// ## Errors
+    ///
+    /// [`Error::DecompressionError`] is returned if the header is truncated
+    /// or if the compression of the payload is not known.
+    ///
+    /// [`payload_len`]: #structfield.payload_len
+    /// [`Error::DecompressionError`]: ./enum.Error.html#variant.DecompressionError
+    pub fn parse(data: &[u8]) -> Result<Self, Error> {
+        let (version, hash, payload_offset, payload_end) = if data.starts_with(SC_MAGIC) {
+            let mut offset = SC_MAGIC.len();
+
+            let mut version = read_u32_be(data, &mut offset)?;
+            let has_metadata = version == 4;
+            if has_metadata {
+                version = read_u32_be(data, &mut offset)?;
+            }
+
+            let hash_length = read_u32_be(data, &mut offset)? as usize;
+            let hash = match data.get(offset..offset + hash_length) {
+                Some(h) => h.to_vec(),
+                None => return Err(truncated()),
+            };
+            offset += hash_length;
+
+            let payload_end = if has_metadata {
+                find_metadata(&data[offset..]).map_or(data.len(), |p| offset + p)
+            } else {
+                data.len()
+            };
+
+            (Some(version), hash, offset, payload_end)
+        } else {
+            (None, Vec::new(), 0, data.len())
+        };
+
+        let compression = match Compression::detect(&data[payload_offset..payload_end]) {
+            Some(c) => c,
+            None => {
+                return Err(Error::DecompressionError(
+                    "Unknown compression format".to_string(),
+                ))
+            }
+        };
+
+        Ok(Self {
+            version,
+            hash,
+            compression,
+            payload_offset,
+            payload_len: payload_end - payload_offset,
+        })
+    }
+
+    /// Checks the hash stored in this header against the decompressed data.
+    ///
+    /// Supercell stores the MD5 hash of the decompressed data in the header.
+    /// Files without an `SC` header carry no hash and always pass the check.
+    ///
+    /// ## Errors
+    ///
+    /// [`Error::HashMismatch`] is returned if the hashes are not equal.
+    ///
+    /// [`Error::HashMismatch`]: ./enum.Error.html#variant.HashMismatch
+    pub fn verify(&self, decompressed: &[u8]) -> Result<(), Error> {
+        if self.hash.is_empty() {
+            return Ok(());
+        }
+
+        let digest = md5::compute(decompressed);
+        if digest.0[..] == self.hash[..] {
+            Ok(())
+        } else {
+            Err(Error::HashMismatch {
+                expected: self.hash.clone(),
+                found: digest.0.to_vec(),
+            })
+        }
+    }
+
+    /// Returns the compressed payload described by this header.
+    ///
+    /// `data` must be the same data the header was parsed from.
+    pub fn payload<'a>(&self, data: &'a [u8]) -> &'a [u8] {
+        &data[self.payload_offset..self.payload_offset + self.payload_len]
+    }
+}
+
+/// Decompresses raw `_tex.sc`, `.sc` or `.csv` file data and verifies the
+/// hash stored in its header.
+///
+/// This can be used to detect truncated or corrupted files before
+/// processing them. The parsed header is returned if the file is intact.
+///
+/// ## Errors
+///
+/// If decompression is unsuccessful, [`Error::DecompressionError`] is returned.
+///
+/// If the hash does not match, [`Error::HashMismatch`] is returned.
+///
+/// [`Error::DecompressionError`]: ./enum.Error.html#variant.DecompressionError
+/// [`Error::HashMismatch`]: ./enum.Error.html#variant.HashMismatch
+pub fn verify(data: &[u8]) -> Result<Header, Error> {
+    let (header, decompressed) = decompress(data)?;
+    header.verify(decompressed.get_ref())?;
+
+    Ok(header)
+}
+
+/// Reads a big-endian `u32` at `offset` and advances the offset.
+fn read_u32_be(data: &[u8], offset: &mut usize) -> Result<u32, Error> {
+    let bytes = match data.get(*offset..*offset + 4) {
+        Some(b) => b,
+        None => return Err(truncated()),
+    };
+    *offset += 4;
+
+    Ok(u32::from_be_bytes(bytes.try_into().unwrap()))
+}
+
+/// Returns the position of the metadata block of a version 4 container.
+fn find_metadata(data: &[u8]) -> Option<usize> {
+    data.windows(METADATA_MARKER.len())
+        .rposition(|w| w == METADATA_MARKER)
+}
+
+fn truncated() -> Error {
+    Error::DecompressionError("File is too small to contain a valid header".to_string())
+}
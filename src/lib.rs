@@ -1,8 +1,23 @@
 //! Library to extract graphics and decode csv files from Supercell's game files.
 //!
 //! The library exposes three high-level functions, [`process_sc`],
-//! [`process_tex`] and [`process_csv`], to process extracted `sc`, `_tex.sc`
-//! and `.csv` files respectively.
+//! [`process_tex`] and [`process_csv`], to process `.sc`, `_tex.sc`
+//! and `.csv` files respectively. [`decode_tex`] and [`cut_sprites`] decode
+//! `_tex.sc` files and cut sprites into in-memory images without writing
+//! anything. The container header of a file can be
+//! inspected with [`Header::parse`] and its integrity checked with [`verify`].
+//! [`ScFile::parse`] parses a `.sc` file into the object model of [`model`],
+//! which [`cut_shapes`] cuts sprites from. [`render_movie_clip`] renders the
+//! frames of its movie clips from the sprites, and [`save_animation`] saves
+//! them as APNG, GIF or WebP animations.
+//!
+//! Decoded images have premultiplied alpha, as stored in the files. The
+//! [`ExportOptions`] passed to the processing functions can convert them to
+//! straight alpha when saving. They also select the image format images are
+//! saved in, PNG by default.
+//!
+//! The library does not write to stdout. Progress and warnings are reported
+//! to an [`Observer`] passed to the processing functions.
 //!
 //! This library is simply intended to get high quality graphics and data from
 //! the files. It is in no way an attempt to:
@@ -14,12 +29,46 @@
 //! [`process_sc`]: ./fn.process_sc.html
 //! [`process_tex`]: ./fn.process_tex.html
 //! [`process_csv`]: ./fn.process_csv.html
+//! [`decode_tex`]: ./fn.decode_tex.html
+//! [`cut_sprites`]: ./fn.cut_sprites.html
+//! [`Header::parse`]: ./struct.Header.html#method.parse
+//! [`verify`]: ./fn.verify.html
+//! [`ScFile::parse`]: ./model/struct.ScFile.html#method.parse
+//! [`model`]: ./model/index.html
+//! [`cut_shapes`]: ./fn.cut_shapes.html
+//! [`render_movie_clip`]: ./fn.render_movie_clip.html
+//! [`save_animation`]: ./fn.save_animation.html
+//! [`ExportOptions`]: ./struct.ExportOptions.html
+//! [`Observer`]: ./trait.Observer.html
 
+mod codecs;
 mod error;
+mod export;
 mod extractors;
+mod header;
+pub mod model;
+mod observer;
+mod render;
 mod utils;
 
 #[doc(inline)]
 pub use error::Error;
 #[doc(inline)]
-pub use extractors::{csv::process_csv, sc::process_sc, tex::process_tex};
+pub use export::{
+    premultiply, save_animation, unpremultiply, AnimationFormat, ExportOptions, OutputFormat,
+    PngCompression, SpriteFallback,
+};
+#[doc(inline)]
+pub use extractors::{
+    csv::process_csv,
+    sc::{cut_shapes, cut_sprites, process_sc, Sprite, TextureVariant},
+    tex::{decode_tex, process_tex, Filter, Texture, TextureTag},
+};
+#[doc(inline)]
+pub use header::{verify, Compression, Header};
+#[doc(inline)]
+pub use model::ScFile;
+#[doc(inline)]
+pub use observer::{Event, Observer};
+#[doc(inline)]
+pub use render::{render_movie_clip, Animation, RenderedFrame};
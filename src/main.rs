@@ -1,17 +1,20 @@
 use colored::Colorize;
 use rayon::prelude::*;
-use sc_extract::{process_csv, process_sc, process_tex};
+use sc_extract::{
+    process_csv, process_sc, process_tex, verify, AnimationFormat, Event, ExportOptions, Header,
+    OutputFormat, PngCompression, SpriteFallback, TextureVariant,
+};
 use std::{
     fs,
-    path::PathBuf,
+    path::{Path, PathBuf},
     str::FromStr,
-    sync::atomic::{AtomicBool, Ordering},
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
 };
 use structopt::StructOpt;
 
 /// A very fast tool to extract graphics and data from Supercell's game files.
 ///
-/// sc_extract supports extraction of the following files: `_tex.sc`, extracted `.sc` and `.csv`.
+/// sc_extract supports extraction of the following files: `_tex.sc`, `.sc` and `.csv`.
 #[derive(StructOpt)]
 #[structopt(name = "sce")]
 struct Options {
@@ -31,18 +34,106 @@ struct Options {
     #[structopt(short = "d", long = "delete")]
     delete: bool,
 
+    /// Verifies the hash of the files instead of extracting them.
+    ///
+    /// The files are decompressed and their content is compared with the
+    /// hash stored in the file header. Nothing is written to the output
+    /// directory.
+    #[structopt(long = "verify")]
+    verify: bool,
+
+    /// Also saves the mip levels stored in `_tex.sc` textures, as `_mipN` images.
+    #[structopt(long = "mipmaps")]
+    mipmaps: bool,
+
+    /// Saves images with straight instead of premultiplied alpha.
+    ///
+    /// This avoids dark fringes around semi-transparent edges. The same flag
+    /// must be used when extracting `_tex.sc` files and cutting sprites from
+    /// their images with `.sc` files.
+    #[structopt(long = "unpremultiply")]
+    unpremultiply: bool,
+
+    /// The image format extracted images are saved in.
+    ///
+    /// Possible values are "png", "webp", "tga", "bmp" and "qoi". By default,
+    /// images are saved as png. When cutting sprites, the images of `_tex.sc`
+    /// files are expected in the same format.
+    #[structopt(short = "f", long = "format", default_value = "png")]
+    format: OutputFormat,
+
+    /// The compression level of png images.
+    ///
+    /// Possible values are "fast", "default" and "best". Better compression
+    /// makes smaller files but takes much longer.
+    #[structopt(long = "png-compression", default_value = "fast")]
+    png_compression: PngCompression,
+
     /// Extracts all images in parallel, making the process faster.
     #[structopt(short = "p", long = "parallelize")]
     parallelize: bool,
 
     /// The path to directory where a `_tex.sc` file's extracted images are stored.
     ///
-    /// It is required for cutting images using extracted `.sc` files. If the
-    /// path is not specified, sc_extract will look for the png files in the
-    /// directory where the source (extracted `.sc`) file(s) is/are present.
+    /// It is required for cutting images using `.sc` files. If the path is
+    /// not specified, sc_extract will look for the png files in the
+    /// directory where the source `.sc` file(s) is/are present.
     #[structopt(parse(from_os_str), short = "P", long = "png")]
     png_dir: Option<PathBuf>,
 
+    /// The texture variant to cut sprites from.
+    ///
+    /// Possible values are "default", "highres" and "lowres", for the images
+    /// of `_tex.sc`, `_highres_tex.sc` and `_lowres_tex.sc` files. By default,
+    /// the first variant with images present is used, in this order.
+    #[structopt(long = "variant")]
+    variant: Option<TextureVariant>,
+
+    /// Names sprites by their index instead of their export names.
+    #[structopt(long = "numbered-sprites")]
+    numbered_sprites: bool,
+
+    /// How sprites of shapes without an export name are named.
+    ///
+    /// Possible values are "index", "id" and "skip". "index" names them
+    /// `{file}_sprite_{index}`, "id" names them `{file}_shape_{id}` and "skip"
+    /// does not save them. By default, "index" is used.
+    #[structopt(long = "sprite-fallback", default_value = "index")]
+    sprite_fallback: SpriteFallback,
+
+    /// Also renders the exported movie clips of `.sc` files frame by frame.
+    ///
+    /// The frames of each movie clip are saved in a directory named after it.
+    #[structopt(long = "movie-clips")]
+    movie_clips: bool,
+
+    /// Also saves the exported movie clips of `.sc` files as animations.
+    ///
+    /// The animation of each movie clip is saved in a directory named after it.
+    #[structopt(long = "animations")]
+    animations: bool,
+
+    /// The format of animations of movie clips.
+    ///
+    /// Possible values are "apng", "gif" and "webp". By default, animations
+    /// are saved as apng.
+    #[structopt(long = "animation-format", default_value = "apng")]
+    animation_format: AnimationFormat,
+
+    /// Renders movie clips without their color transforms.
+    ///
+    /// Movie clips use color transforms to tint and fade their children, such
+    /// as for team colors. This flag gives the untinted base art.
+    #[structopt(long = "no-color-transforms")]
+    no_color_transforms: bool,
+
+    /// Only renders the sub-animation of movie clips starting at the frame
+    /// with this label, such as "idle" or "attack".
+    ///
+    /// Movie clips without a frame with the label are skipped.
+    #[structopt(long = "label")]
+    label: Option<String>,
+
     /// Specifies the type of files you want to extract.
     ///
     /// Possible values are "csv", "sc" and "tex". By default, all types are considered.
@@ -94,6 +185,44 @@ fn delete_file(path: &PathBuf) {
     };
 }
 
+/// Returns the export options selected by the command line flags.
+fn export_options(opts: &Options) -> ExportOptions {
+    ExportOptions {
+        format: opts.format,
+        png_compression: opts.png_compression,
+        texture_variant: opts.variant,
+        numbered_sprites: opts.numbered_sprites,
+        sprite_fallback: opts.sprite_fallback,
+        movie_clips: opts.movie_clips,
+        animations: opts.animations,
+        animation_format: opts.animation_format,
+        ignore_color_transforms: opts.no_color_transforms,
+        frame_label: opts.label.clone(),
+        mipmaps: opts.mipmaps,
+        unpremultiply: opts.unpremultiply,
+    }
+}
+
+/// Prints the events emitted by the library on stdout.
+fn print_event(event: &Event) {
+    match event {
+        Event::TextureStarted {
+            tag,
+            pixel_format,
+            width,
+            height,
+        } => println!(
+            "file_type: {}, sub_type: {}, width: {}, height: {}",
+            tag.to_string().cyan().bold(),
+            pixel_format.to_string().cyan().bold(),
+            width.to_string().cyan().bold(),
+            height.to_string().cyan().bold()
+        ),
+        Event::Warning(e) => println!("Error: {}", e.to_string().red()),
+        _ => (),
+    }
+}
+
 /// Returns correct file type depending on the file extension and/or data.
 ///
 /// If the extension and/or data don't match any expected file type,
@@ -108,13 +237,20 @@ fn get_file_type(data: &[u8], path: &PathBuf, filter: bool) -> Option<FileType>
         return None;
     }
 
-    if data.is_empty() {
+    if data.is_empty() || Header::parse(data).is_err() {
         None
-    } else if path.extension().is_none() {
-        Some(FileType::Sc)
-    } else if data[0] == 83 && path_str.ends_with("_tex.sc") {
+    } else {
+        file_type_from_name(path_str)
+    }
+}
+
+/// Returns the file type matching the name of a file, if any.
+fn file_type_from_name(name: &str) -> Option<FileType> {
+    if name.ends_with("_tex.sc") {
         Some(FileType::Tex)
-    } else if data.len() >= 2 && data[..2] == [93, 0] && path_str.ends_with(".csv") {
+    } else if name.ends_with(".sc") {
+        Some(FileType::Sc)
+    } else if name.ends_with(".csv") {
         Some(FileType::Csv)
     } else {
         None
@@ -123,10 +259,14 @@ fn get_file_type(data: &[u8], path: &PathBuf, filter: bool) -> Option<FileType>
 
 /// Processes the given file (path).
 ///
-/// It automatically detects file type (`_tex.sc`, `.csv` or extracted `.sc`)
+/// It automatically detects file type (`_tex.sc`, `.csv` or `.sc`)
 /// and processes them appropriately. If processing a file fails, formatted
 /// error messages gets printed on `stdout`.
 ///
+/// When verifying, files that fail verification are printed on `stderr` and
+/// counted in `failures`. This includes files named like game files whose
+/// header cannot be read.
+///
 /// ## Panic
 ///
 /// The process may panic in case of lack of permissions to read/write files.
@@ -135,6 +275,7 @@ fn process_file(
     out_dir: &PathBuf,
     parallelize: bool,
     opts: &Options,
+    failures: &AtomicUsize,
 ) -> Result<(), ()> {
     let data = match fs::read(&path) {
         Ok(d) => d,
@@ -153,9 +294,40 @@ fn process_file(
             .to_str()
             .expect("Expected file to have a valid UTF-8 name.");
 
+        if opts.verify {
+            match verify(&data) {
+                Ok(_) => println!("{}: {}", "Verified".green(), path.to_str().unwrap()),
+                Err(e) => {
+                    eprintln!("{}: {}", e.to_string().red(), path.to_str().unwrap().red());
+                    failures.fetch_add(1, Ordering::SeqCst);
+                }
+            }
+
+            return Ok(());
+        }
+
         match file_type {
-            FileType::Tex => process_tex(&data, file_name, &out_dir, parallelize),
-            FileType::Csv => process_csv(&data, file_name, &out_dir),
+            FileType::Tex => {
+                if !parallelize {
+                    println!("\nExtracting {} image(s)...", file_name);
+                }
+
+                let tex_dir = path.parent().unwrap_or_else(|| Path::new("."));
+
+                process_tex(
+                    &data,
+                    file_name,
+                    &out_dir,
+                    tex_dir,
+                    &export_options(opts),
+                    &print_event,
+                )
+            }
+            FileType::Csv => {
+                println!("\nExtracting {} file...", file_name.green().bold());
+
+                process_csv(&data, file_name, &out_dir)
+            }
             FileType::Sc => {
                 let png_dir = match opts.png_dir.as_ref() {
                     Some(p) => p,
@@ -169,21 +341,53 @@ fn process_file(
                     },
                 };
 
-                let out_dir = out_dir.join(format!("{}_out", file_name));
+                let out_dir = out_dir.join(format!("{}_out", file_name.trim_end_matches(".sc")));
                 if !out_dir.exists() {
                     // We want to panic if a directory can't be created.
                     fs::create_dir(&out_dir).unwrap();
                 }
 
-                process_sc(&data, file_name, &out_dir, png_dir, parallelize)
+                if !parallelize {
+                    println!("\nProcessing `{}` image(s)...", file_name.green().bold());
+                }
+
+                process_sc(
+                    &data,
+                    file_name,
+                    &out_dir,
+                    png_dir,
+                    &export_options(opts),
+                    &print_event,
+                )
             }
         }
     } else {
+        let named_type = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .and_then(file_type_from_name);
+
+        // Files named like game files but with an unreadable header fail
+        // verification instead of being skipped.
+        if opts.verify && named_type.is_some_and(|t| opts.kind.is_none_or(|k| k == t)) {
+            let e = Header::parse(&data)
+                .err()
+                .map_or_else(|| "Could not read the file".to_string(), |e| e.to_string());
+            eprintln!("{}: {}", e.red(), path.to_str().unwrap().red());
+            failures.fetch_add(1, Ordering::SeqCst);
+
+            return Ok(());
+        }
+
         return Err(());
     };
 
     if let Err(e) = res {
-        println!("\n{}: {}", e.inner().red(), path.to_str().unwrap().red());
+        println!(
+            "\n{}: {}",
+            e.to_string().red(),
+            path.to_str().unwrap().red()
+        );
 
         // Don't delete file if there was an error.
         return Ok(());
@@ -233,6 +437,8 @@ fn main() {
         }
     };
 
+    let failures = AtomicUsize::new(0);
+
     let created_out = if !out_dir.exists() {
         fs::create_dir_all(&out_dir).expect("Expected to be able to create a directory.");
 
@@ -268,14 +474,14 @@ fn main() {
         if opts.parallelize {
             entries.into_par_iter().for_each(|entry| {
                 let file_path = entry.unwrap().path();
-                if process_file(&file_path, &out_dir, true, &opts).is_ok() {
+                if process_file(&file_path, &out_dir, true, &opts, &failures).is_ok() {
                     found_one.compare_and_swap(false, true, Ordering::AcqRel);
                 }
             })
         } else {
             for entry in entries {
                 let file_path = entry.unwrap().path();
-                if process_file(&file_path, &out_dir, false, &opts).is_ok() {
+                if process_file(&file_path, &out_dir, false, &opts, &failures).is_ok() {
                     found_one.compare_and_swap(false, true, Ordering::AcqRel);
                 }
             }
@@ -291,7 +497,7 @@ fn main() {
             std::process::exit(1);
         }
     } else if path.is_file() {
-        let _ = process_file(&path, &out_dir, false, &opts);
+        let _ = process_file(&path, &out_dir, false, &opts, &failures);
     }
 
     if created_out {
@@ -299,5 +505,20 @@ fn main() {
         let _ = fs::remove_dir(&out_dir);
     }
 
-    println!("\n{}", "Extraction finished!".green().bold());
+    if opts.verify {
+        let failures = failures.into_inner();
+        if failures > 0 {
+            eprintln!(
+                "\n{}",
+                format!("{} file(s) failed verification!", failures)
+                    .red()
+                    .bold()
+            );
+            std::process::exit(1);
+        }
+
+        println!("\n{}", "Verification finished!".green().bold());
+    } else {
+        println!("\n{}", "Extraction finished!".green().bold());
+    }
 }
@@ -0,0 +1,798 @@
+//! Object model of `.sc` files.
+//!
+//! A `.sc` file describes the graphics of a game: shapes cut out of the
+//! textures of the corresponding `_tex.sc` file, movie clips animating them,
+//! text fields and the matrices and color transforms used to place them.
+//! [`ScFile::parse`] reads all of them into the types of this module.
+//!
+//! [`ScFile::parse`]: ./struct.ScFile.html#method.parse
+
+use crate::{
+    error::Error,
+    extractors::tex::TextureTag,
+    utils::{decompress, Reader},
+};
+use std::collections::{HashMap, HashSet};
+
+/// Index used by frame elements that have no matrix or color transform.
+const NONE_INDEX: u16 = 0xFFFF;
+
+/// Positions are stored in twips, which are `1/20` of a pixel.
+pub(crate) const TWIPS_PER_PIXEL: f32 = 20.0;
+
+/// A parsed `.sc` file.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ScFile {
+    /// Textures of the `_tex.sc` file, in the order they are stored.
+    pub textures: Vec<Texture>,
+    /// Shapes cut out of the textures.
+    pub shapes: Vec<Shape>,
+    /// Movie clips placing shapes, text fields and other movie clips.
+    pub movie_clips: Vec<MovieClip>,
+    /// Text fields.
+    pub text_fields: Vec<TextField>,
+    /// Banks of the matrices and color transforms referenced by frame
+    /// elements of movie clips.
+    ///
+    /// The first bank is declared by the header of the file. Newer files
+    /// start more banks with tag `42`, and every movie clip selects the bank
+    /// it uses.
+    pub matrix_banks: Vec<MatrixBank>,
+    /// Named objects the game refers to, usually movie clips.
+    pub exports: Vec<Export>,
+}
+
+/// A texture of the `_tex.sc` file, as declared by the `.sc` file.
+///
+/// The `.sc` file only stores the tag, pixel format and size of a texture.
+/// Its pixels are stored in the `_tex.sc` file.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Texture {
+    /// Tag of the texture block.
+    pub tag: TextureTag,
+    /// Pixel format of the texture.
+    pub pixel_format: u8,
+    /// Width of the texture, in pixels.
+    pub width: u32,
+    /// Height of the texture, in pixels.
+    pub height: u32,
+}
+
+/// A shape, made of regions cut out of textures.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Shape {
+    /// ID of the shape.
+    pub id: u16,
+    /// Regions of the shape, drawn in order.
+    pub regions: Vec<Region>,
+}
+
+/// A polygon cut out of a texture and placed in a shape.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Region {
+    /// Tag of the region, which determines the unit of `uvs`.
+    pub tag: u8,
+    /// Index of the texture the region is cut out of.
+    pub texture: u8,
+    /// Corners of the polygon in the shape, in pixels.
+    pub points: Vec<(f32, f32)>,
+    /// Corners of the polygon in the texture, in the same order as `points`.
+    ///
+    /// For tag `22`, they are scaled so that `65535` is the width or height of
+    /// the texture. For tags `4` and `17`, they are in pixels.
+    pub uvs: Vec<(u16, u16)>,
+}
+
+impl Region {
+    /// Returns the corners of the polygon in a texture of the given size, in
+    /// pixels.
+    pub fn texture_points(&self, width: u32, height: u32) -> Vec<(f32, f32)> {
+        self.uvs
+            .iter()
+            .map(|&(u, v)| {
+                if self.tag == 22 {
+                    (
+                        u as f32 * width as f32 / 65535.0,
+                        v as f32 * height as f32 / 65535.0,
+                    )
+                } else {
+                    (u as f32, v as f32)
+                }
+            })
+            .collect()
+    }
+}
+
+/// An animation made of frames, each placing children of the movie clip.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MovieClip {
+    /// ID of the movie clip.
+    pub id: u16,
+    /// Frames per second of the animation.
+    pub fps: u8,
+    /// Objects the frames can place, by index.
+    pub children: Vec<Child>,
+    /// Frames of the animation, in order.
+    pub frames: Vec<Frame>,
+    /// The 9-slice scaling grid of the movie clip, if it has one.
+    pub scaling_grid: Option<Rect>,
+    /// Index of the bank of the matrices and color transforms referenced by
+    /// the frame elements.
+    pub matrix_bank: u8,
+}
+
+/// An object placed by the frames of a movie clip.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Child {
+    /// ID of the shape, movie clip or text field.
+    pub id: u16,
+    /// Blend mode the child is drawn with. `0` is normal blending.
+    pub blend_mode: u8,
+    /// Name the game refers to the child by, if any.
+    pub name: Option<String>,
+}
+
+/// A single frame of a movie clip.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Frame {
+    /// Label of the frame, if any. Labels mark the start of animations.
+    pub label: Option<String>,
+    /// Children drawn in the frame, from the bottom to the top.
+    pub elements: Vec<FrameElement>,
+}
+
+/// A child drawn in a frame.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FrameElement {
+    /// Index of the child in the children of the movie clip.
+    pub child: u16,
+    /// Index of the matrix positioning the child, if any.
+    pub matrix: Option<u16>,
+    /// Index of the color transform applied to the child, if any.
+    pub color_transform: Option<u16>,
+}
+
+/// A rectangle, in pixels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rect {
+    /// Left edge of the rectangle.
+    pub x: f32,
+    /// Top edge of the rectangle.
+    pub y: f32,
+    /// Width of the rectangle.
+    pub width: f32,
+    /// Height of the rectangle.
+    pub height: f32,
+}
+
+/// A text field.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextField {
+    /// ID of the text field.
+    pub id: u16,
+    /// Name of the font, if any.
+    pub font_name: Option<String>,
+    /// Color of the text, packed as `0xAARRGGBB`.
+    pub color: u32,
+    /// Whether the text is bold.
+    pub bold: bool,
+    /// Whether the text is italic.
+    pub italic: bool,
+    /// Whether the text wraps to multiple lines.
+    pub multiline: bool,
+    /// Alignment of the text.
+    pub align: u8,
+    /// Size of the font.
+    pub font_size: u8,
+    /// Left edge of the text field.
+    pub left: i16,
+    /// Top edge of the text field.
+    pub top: i16,
+    /// Right edge of the text field.
+    pub right: i16,
+    /// Bottom edge of the text field.
+    pub bottom: i16,
+    /// Whether the text is outlined.
+    pub outlined: bool,
+    /// Placeholder text of the text field, if any.
+    pub text: Option<String>,
+    /// Whether the text is drawn with a font of the device.
+    pub use_device_font: bool,
+}
+
+/// Matrices and color transforms referenced by the frame elements of movie
+/// clips.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MatrixBank {
+    /// Matrices, referenced by index.
+    pub matrices: Vec<Matrix>,
+    /// Color transforms, referenced by index.
+    pub color_transforms: Vec<ColorTransform>,
+}
+
+/// An affine transform.
+///
+/// A point `(x, y)` is transformed to `(a * x + c * y + tx, b * x + d * y + ty)`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Matrix {
+    /// Horizontal scale.
+    pub a: f32,
+    /// Vertical shear.
+    pub b: f32,
+    /// Horizontal shear.
+    pub c: f32,
+    /// Vertical scale.
+    pub d: f32,
+    /// Horizontal translation, in pixels.
+    pub tx: f32,
+    /// Vertical translation, in pixels.
+    pub ty: f32,
+}
+
+impl Matrix {
+    /// Transforms the point `(x, y)`.
+    pub fn transform(&self, x: f32, y: f32) -> (f32, f32) {
+        (
+            self.a * x + self.c * y + self.tx,
+            self.b * x + self.d * y + self.ty,
+        )
+    }
+
+    /// Returns the matrix applying `other` first, then `self`.
+    ///
+    /// A child placed with `other` inside a movie clip placed with `self` is
+    /// placed with the returned matrix.
+    pub fn multiply(&self, other: &Matrix) -> Matrix {
+        let (tx, ty) = self.transform(other.tx, other.ty);
+
+        Matrix {
+            a: self.a * other.a + self.c * other.b,
+            b: self.b * other.a + self.d * other.b,
+            c: self.a * other.c + self.c * other.d,
+            d: self.b * other.c + self.d * other.d,
+            tx,
+            ty,
+        }
+    }
+}
+
+impl Default for Matrix {
+    fn default() -> Self {
+        Self {
+            a: 1.0,
+            b: 0.0,
+            c: 0.0,
+            d: 1.0,
+            tx: 0.0,
+            ty: 0.0,
+        }
+    }
+}
+
+/// A transform of the colors of an object.
+///
+/// Every channel is multiplied by its multiplier divided by `255`, then the
+/// additive term is added to it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ColorTransform {
+    /// Multipliers of the red, green, blue and alpha channels.
+    pub multiply: [u8; 4],
+    /// Additive terms of the red, green and blue channels.
+    pub add: [u8; 3],
+}
+
+impl ColorTransform {
+    /// Returns the color transform applying `other` first, then `self`.
+    ///
+    /// A child transformed with `other` inside a movie clip transformed with
+    /// `self` is transformed with the returned color transform.
+    pub fn combine(&self, other: &ColorTransform) -> ColorTransform {
+        let mut combined = ColorTransform::default();
+        for c in 0..4 {
+            combined.multiply[c] = mul_div_255(self.multiply[c], other.multiply[c]);
+        }
+        for c in 0..3 {
+            combined.add[c] =
+                mul_div_255(other.add[c], self.multiply[c]).saturating_add(self.add[c]);
+        }
+
+        combined
+    }
+
+    /// Transforms a pixel with premultiplied alpha.
+    ///
+    /// The additive terms are scaled by the transformed alpha, so that fully
+    /// transparent pixels stay transparent.
+    pub fn apply_premultiplied(&self, pixel: [u8; 4]) -> [u8; 4] {
+        let alpha = mul_div_255(pixel[3], self.multiply[3]);
+
+        let mut transformed = [0, 0, 0, alpha];
+        for c in 0..3 {
+            let color = mul_div_255(mul_div_255(pixel[c], self.multiply[c]), self.multiply[3]);
+            transformed[c] = color
+                .saturating_add(mul_div_255(self.add[c], alpha))
+                .min(alpha);
+        }
+
+        transformed
+    }
+}
+
+impl Default for ColorTransform {
+    fn default() -> Self {
+        Self {
+            multiply: [255; 4],
+            add: [0; 3],
+        }
+    }
+}
+
+/// Returns `a * b / 255`, rounded.
+fn mul_div_255(a: u8, b: u8) -> u8 {
+    ((a as u32 * b as u32 + 127) / 255) as u8
+}
+
+/// A named object.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Export {
+    /// ID of the object, usually a movie clip.
+    pub id: u16,
+    /// Name the game refers to the object by.
+    pub name: String,
+}
+
+impl ScFile {
+    /// Parses compressed, raw `.sc` file data.
+    ///
+    /// Tags that are not part of the model are skipped.
+    ///
+    /// The parser follows the layout of game `.sc` files, but no game file is
+    /// part of the tests: it is only tested on synthetic files written in that
+    /// layout.
+    ///
+    /// ## Errors
+    ///
+    /// If decompression is unsuccessful, [`Error::DecompressionError`] is returned.
+    ///
+    /// If the decompressed data is truncated, [`Error::UnexpectedEof`] is returned.
+    ///
+    /// If a tag is invalid at its position, such as a region referring to a
+    /// texture the file does not have, [`Error::BadTag`] is returned.
+    ///
+    /// [`Error::DecompressionError`]: ../error/enum.Error.html#variant.DecompressionError
+    /// [`Error::UnexpectedEof`]: ../error/enum.Error.html#variant.UnexpectedEof
+    /// [`Error::BadTag`]: ../error/enum.Error.html#variant.BadTag
+    pub fn parse(data: &[u8]) -> Result<Self, Error> {
+        let (_, decompressed) = decompress(data)?;
+
+        Self::read(Reader::new(decompressed))
+    }
+
+    /// Returns the names of exported shapes, by shape ID.
+    ///
+    /// Exported movie clips are followed down to the shapes they place. If an
+    /// export reaches a single shape, the shape is named after the export.
+    /// Otherwise, the index of the shape among the reached ones is appended to
+    /// the name, like `hero_idle_0`. Shapes reached by multiple exports are
+    /// named after the first one.
+    pub fn shape_names(&self) -> HashMap<u16, String> {
+        let clips: HashMap<_, _> = self.movie_clips.iter().map(|c| (c.id, c)).collect();
+
+        let mut names = HashMap::new();
+        for export in &self.exports {
+            let mut shapes = Vec::new();
+            self.collect_shapes(export.id, &clips, &mut HashSet::new(), &mut shapes);
+
+            if shapes.len() == 1 {
+                names
+                    .entry(shapes[0])
+                    .or_insert_with(|| export.name.clone());
+            } else {
+                for (i, shape) in shapes.into_iter().enumerate() {
+                    names
+                        .entry(shape)
+                        .or_insert_with(|| format!("{}_{}", export.name, i));
+                }
+            }
+        }
+
+        names
+    }
+
+    /// Collects the IDs of the shapes reachable from the object `id` into
+    /// `shapes`, in the order the movie clips list them.
+    fn collect_shapes(
+        &self,
+        id: u16,
+        clips: &HashMap<u16, &MovieClip>,
+        visited: &mut HashSet<u16>,
+        shapes: &mut Vec<u16>,
+    ) {
+        if self.shapes.iter().any(|s| s.id == id) {
+            if !shapes.contains(&id) {
+                shapes.push(id);
+            }
+        } else if let Some(clip) = clips.get(&id) {
+            // Movie clips can contain themselves.
+            if visited.insert(id) {
+                for child in &clip.children {
+                    self.collect_shapes(child.id, clips, visited, shapes);
+                }
+            }
+        }
+    }
+
+    /// Reads a `.sc` file from decompressed data.
+    pub(crate) fn read(mut stream: Reader) -> Result<Self, Error> {
+        let shape_count = stream.read_uint16()? as usize;
+        let _movie_clip_count = stream.read_uint16()?;
+        let texture_count = stream.read_uint16()? as usize;
+        let _text_field_count = stream.read_uint16()?;
+        let _matrix_count = stream.read_uint16()?;
+        let _color_transform_count = stream.read_uint16()?;
+
+        // Unused bytes before the exports.
+        stream.skip(5)?;
+
+        let export_count = stream.read_uint16()? as usize;
+        let mut ids = Vec::with_capacity(export_count);
+        for _ in 0..export_count {
+            ids.push(stream.read_uint16()?);
+        }
+
+        let mut file = Self {
+            matrix_banks: vec![MatrixBank::default()],
+            ..Self::default()
+        };
+        for id in ids {
+            let length = stream.read_byte()? as usize;
+            let name = stream.read_string(length)?;
+            file.exports.push(Export { id, name });
+        }
+
+        while stream.len() > 0 {
+            let tag_offset = stream.offset();
+            let tag = stream.read_byte()?;
+            let tag_size = stream.read_uint32()? as usize;
+            let tag_end = stream.offset() + tag_size;
+
+            let bad_tag = Error::BadTag {
+                tag,
+                offset: tag_offset,
+            };
+
+            if let Some(texture_tag) = TextureTag::from_tag(tag) {
+                if file.textures.len() >= texture_count {
+                    return Err(bad_tag);
+                }
+
+                file.textures.push(Texture {
+                    tag: texture_tag,
+                    pixel_format: stream.read_byte()?,
+                    width: stream.read_uint16()?.into(),
+                    height: stream.read_uint16()?.into(),
+                });
+            } else {
+                match tag {
+                    // End of the file.
+                    0 => break,
+                    2 | 18 => {
+                        if file.shapes.len() >= shape_count {
+                            return Err(bad_tag);
+                        }
+
+                        file.shapes
+                            .push(read_shape(&mut stream, tag, texture_count)?);
+                    }
+                    7 | 15 | 20 | 21 | 25 | 33 | 43 | 44 => {
+                        file.text_fields.push(read_text_field(&mut stream, tag)?);
+                    }
+                    8 | 36 => {
+                        let matrix = read_matrix(&mut stream, tag)?;
+                        current_bank(&mut file).matrices.push(matrix);
+                    }
+                    9 => {
+                        let color_transform = read_color_transform(&mut stream)?;
+                        current_bank(&mut file)
+                            .color_transforms
+                            .push(color_transform);
+                    }
+                    // Start of a new matrix bank. Its header only holds the
+                    // counts of its matrices and color transforms.
+                    42 => file.matrix_banks.push(MatrixBank::default()),
+                    12 | 35 => {
+                        file.movie_clips
+                            .push(read_movie_clip(&mut stream, tag, tag_offset)?);
+                    }
+                    _ => (),
+                }
+            }
+
+            // Tags may hold more data than is part of the model. Some tags
+            // are followed by sub-tags not counted in their size.
+            if stream.offset() < tag_end {
+                stream.seek(tag_end)?;
+            }
+        }
+
+        Ok(file)
+    }
+}
+
+/// Returns the bank matrices and color transforms are currently read into.
+fn current_bank(file: &mut ScFile) -> &mut MatrixBank {
+    file.matrix_banks
+        .last_mut()
+        .expect("file has at least one matrix bank")
+}
+
+/// Reads a string prefixed by its length. A length of `255` means no string.
+fn read_optional_string(stream: &mut Reader) -> Result<Option<String>, Error> {
+    let length = stream.read_byte()?;
+    if length == 255 {
+        Ok(None)
+    } else {
+        Ok(Some(stream.read_string(length as usize)?))
+    }
+}
+
+/// Reads a shape tag and its regions.
+fn read_shape(stream: &mut Reader, tag: u8, texture_count: usize) -> Result<Shape, Error> {
+    let id = stream.read_uint16()?;
+    let region_count = stream.read_uint16()? as usize;
+    if tag == 18 {
+        let _point_count = stream.read_uint16()?;
+    }
+
+    let mut regions = Vec::with_capacity(region_count);
+    loop {
+        let region_offset = stream.offset();
+        let region_tag = stream.read_byte()?;
+        let region_size = stream.read_uint32()? as usize;
+        let region_end = stream.offset() + region_size;
+
+        let bad_tag = Error::BadTag {
+            tag: region_tag,
+            offset: region_offset,
+        };
+
+        match region_tag {
+            // End of the shape.
+            0 => break,
+            4 | 17 | 22 => {
+                let texture = stream.read_byte()?;
+                let point_count = if region_tag == 4 {
+                    4
+                } else {
+                    stream.read_byte()? as usize
+                };
+
+                if texture as usize >= texture_count || point_count < 3 {
+                    return Err(bad_tag);
+                }
+
+                let mut points = Vec::with_capacity(point_count);
+                for _ in 0..point_count {
+                    points.push((
+                        stream.read_int32()? as f32 / TWIPS_PER_PIXEL,
+                        stream.read_int32()? as f32 / TWIPS_PER_PIXEL,
+                    ));
+                }
+
+                let mut uvs = Vec::with_capacity(point_count);
+                for _ in 0..point_count {
+                    uvs.push((stream.read_uint16()?, stream.read_uint16()?));
+                }
+
+                regions.push(Region {
+                    tag: region_tag,
+                    texture,
+                    points,
+                    uvs,
+                });
+            }
+            _ => return Err(bad_tag),
+        }
+
+        if stream.offset() < region_end {
+            stream.seek(region_end)?;
+        }
+    }
+
+    Ok(Shape { id, regions })
+}
+
+/// Reads a movie clip tag and its frames.
+fn read_movie_clip(stream: &mut Reader, tag: u8, tag_offset: usize) -> Result<MovieClip, Error> {
+    let bad_tag = || Error::BadTag {
+        tag,
+        offset: tag_offset,
+    };
+
+    let id = stream.read_uint16()?;
+    let fps = stream.read_byte()?;
+    let frame_count = stream.read_uint16()? as usize;
+
+    let element_count = stream.read_int32()?;
+    if element_count < 0 {
+        return Err(bad_tag());
+    }
+
+    // Counts are not trusted for preallocation, each element taking 6 bytes.
+    let mut elements = Vec::with_capacity((element_count as usize).min(stream.len() / 6));
+    for _ in 0..element_count {
+        let child = stream.read_uint16()?;
+        let matrix = stream.read_uint16()?;
+        let color_transform = stream.read_uint16()?;
+
+        elements.push(FrameElement {
+            child,
+            matrix: Some(matrix).filter(|m| *m != NONE_INDEX),
+            color_transform: Some(color_transform).filter(|c| *c != NONE_INDEX),
+        });
+    }
+
+    let child_count = stream.read_int16()?;
+    if child_count < 0 {
+        return Err(bad_tag());
+    }
+
+    let mut children = Vec::with_capacity(child_count as usize);
+    for _ in 0..child_count {
+        children.push(Child {
+            id: stream.read_uint16()?,
+            blend_mode: 0,
+            name: None,
+        });
+    }
+    for child in &mut children {
+        child.blend_mode = stream.read_byte()?;
+    }
+    for child in &mut children {
+        child.name = read_optional_string(stream)?;
+    }
+
+    let mut clip = MovieClip {
+        id,
+        fps,
+        children,
+        // Each frame tag takes at least 5 bytes.
+        frames: Vec::with_capacity(frame_count.min(stream.len() / 5)),
+        scaling_grid: None,
+        matrix_bank: 0,
+    };
+
+    let mut elements = elements.into_iter();
+    loop {
+        let frame_offset = stream.offset();
+        let frame_tag = stream.read_byte()?;
+        let frame_size = stream.read_uint32()? as usize;
+        let frame_end = stream.offset() + frame_size;
+
+        match frame_tag {
+            // End of the movie clip.
+            0 => break,
+            11 => {
+                let count = stream.read_uint16()? as usize;
+                let label = read_optional_string(stream)?;
+
+                let frame_elements: Vec<_> = elements.by_ref().take(count).collect();
+                if frame_elements.len() < count {
+                    return Err(Error::BadTag {
+                        tag: frame_tag,
+                        offset: frame_offset,
+                    });
+                }
+
+                clip.frames.push(Frame {
+                    label,
+                    elements: frame_elements,
+                });
+            }
+            31 => {
+                let x = stream.read_int32()? as f32 / TWIPS_PER_PIXEL;
+                let y = stream.read_int32()? as f32 / TWIPS_PER_PIXEL;
+                let width = stream.read_int32()? as f32 / TWIPS_PER_PIXEL;
+                let height = stream.read_int32()? as f32 / TWIPS_PER_PIXEL;
+
+                clip.scaling_grid = Some(Rect {
+                    x,
+                    y,
+                    width,
+                    height,
+                });
+            }
+            41 => clip.matrix_bank = stream.read_byte()?,
+            _ => (),
+        }
+
+        if stream.offset() < frame_end {
+            stream.seek(frame_end)?;
+        }
+    }
+
+    Ok(clip)
+}
+
+/// Reads a text field tag.
+///
+/// Newer text field tags store more properties after the ones of the model,
+/// which are skipped.
+fn read_text_field(stream: &mut Reader, tag: u8) -> Result<TextField, Error> {
+    let id = stream.read_uint16()?;
+    let font_name = read_optional_string(stream)?;
+    let color = stream.read_uint32()?;
+    let bold = stream.read_byte()? != 0;
+    let italic = stream.read_byte()? != 0;
+    let multiline = stream.read_byte()? != 0;
+    let _unused = stream.read_byte()?;
+    let align = stream.read_byte()?;
+    let font_size = stream.read_byte()?;
+    let left = stream.read_int16()?;
+    let top = stream.read_int16()?;
+    let right = stream.read_int16()?;
+    let bottom = stream.read_int16()?;
+    let outlined = stream.read_byte()? != 0;
+    let text = read_optional_string(stream)?;
+    let use_device_font = tag != 7 && stream.read_byte()? != 0;
+
+    Ok(TextField {
+        id,
+        font_name,
+        color,
+        bold,
+        italic,
+        multiline,
+        align,
+        font_size,
+        left,
+        top,
+        right,
+        bottom,
+        outlined,
+        text,
+        use_device_font,
+    })
+}
+
+/// Reads a matrix tag.
+///
+/// The scale and shear of tag `8` are stored in units of `1/1024`, and those
+/// of tag `36` in units of `1/65535`. Translations are stored in twips, and
+/// converted to pixels.
+///
+/// Tag `36` holds the more precise matrices of newer files, with the same
+/// 32-bit fields as tag `8`. The format has no public specification: its
+/// unit is the one XCoder, an open source `.sc` editor, reads them with, and
+/// no file with tag `36` is available to the tests to confirm it.
+fn read_matrix(stream: &mut Reader, tag: u8) -> Result<Matrix, Error> {
+    let divider = if tag == 36 { 65535.0 } else { 1024.0 };
+
+    Ok(Matrix {
+        a: stream.read_int32()? as f32 / divider,
+        b: stream.read_int32()? as f32 / divider,
+        c: stream.read_int32()? as f32 / divider,
+        d: stream.read_int32()? as f32 / divider,
+        tx: stream.read_int32()? as f32 / TWIPS_PER_PIXEL,
+        ty: stream.read_int32()? as f32 / TWIPS_PER_PIXEL,
+    })
+}
+
+/// Reads a color transform tag.
+///
+/// The additive terms come first, followed by the multipliers starting with
+/// alpha.
+fn read_color_transform(stream: &mut Reader) -> Result<ColorTransform, Error> {
+    let mut add = [0; 3];
+    for value in &mut add {
+        *value = stream.read_byte()?;
+    }
+
+    let alpha = stream.read_byte()?;
+    let mut multiply = [0, 0, 0, alpha];
+    for value in &mut multiply[..3] {
+        *value = stream.read_byte()?;
+    }
+
+    Ok(ColorTransform { multiply, add })
+}
@@ -0,0 +1,76 @@
+use crate::error::Error;
+use std::path::Path;
+
+/// An event emitted while processing files.
+#[derive(Debug)]
+pub enum Event<'a> {
+    /// Emitted when decoding of a texture starts.
+    TextureStarted {
+        /// Tag of the texture block.
+        tag: u8,
+        /// Pixel format of the texture.
+        pixel_format: u8,
+        /// Width of the texture, in pixels.
+        width: u32,
+        /// Height of the texture, in pixels.
+        height: u32,
+    },
+    /// Emitted when a texture has been decoded.
+    TextureDecoded {
+        /// Index of the texture in the file.
+        index: usize,
+        /// Width of the texture, in pixels.
+        width: u32,
+        /// Height of the texture, in pixels.
+        height: u32,
+    },
+    /// Emitted when a sprite has been saved.
+    SpriteWritten {
+        /// ID of the shape the sprite was cut from.
+        shape_id: u16,
+        /// The path the sprite was saved to.
+        path: &'a Path,
+    },
+    /// Emitted when a rendered frame of a movie clip has been saved.
+    FrameWritten {
+        /// ID of the movie clip.
+        clip_id: u16,
+        /// Index of the frame in the movie clip.
+        frame: usize,
+        /// The path the frame was saved to.
+        path: &'a Path,
+    },
+    /// Emitted when the animation of a movie clip has been saved.
+    AnimationWritten {
+        /// ID of the movie clip.
+        clip_id: u16,
+        /// The path the animation was saved to.
+        path: &'a Path,
+    },
+    /// Emitted when a recoverable error occurs. Processing continues after it.
+    Warning(&'a Error),
+}
+
+/// Receives the events emitted while processing files.
+///
+/// The library never writes to stdout itself. Pass `&()` to ignore all
+/// events, or a closure taking an [`Event`] to handle them.
+///
+/// [`Event`]: ./enum.Event.html
+pub trait Observer: Sync {
+    /// Called for every event, possibly from multiple threads.
+    fn on_event(&self, event: &Event<'_>);
+}
+
+impl Observer for () {
+    fn on_event(&self, _: &Event<'_>) {}
+}
+
+impl<F> Observer for F
+where
+    F: Fn(&Event<'_>) + Sync,
+{
+    fn on_event(&self, event: &Event<'_>) {
+        self(event)
+    }
+}
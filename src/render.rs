@@ -0,0 +1,329 @@
+use crate::{
+    error::Error,
+    extractors::sc::{composite_over, Sprite},
+    model::{ColorTransform, Matrix, MovieClip, ScFile},
+};
+use image::{Rgba, RgbaImage};
+use imageproc::geometric_transformations::{warp_into, Interpolation, Projection};
+use rayon::prelude::*;
+use std::collections::HashMap;
+
+/// Maximum depth of nested movie clips. Deeper clips are not drawn, which
+/// also stops clips containing themselves.
+const MAX_DEPTH: usize = 64;
+
+/// A movie clip rendered frame by frame.
+#[derive(Clone, Debug)]
+pub struct Animation {
+    /// ID of the rendered movie clip.
+    pub clip_id: u16,
+    /// Frames per second of the animation.
+    pub fps: u8,
+    /// Frames of the animation, in order. All of them have the same size.
+    pub frames: Vec<RenderedFrame>,
+}
+
+impl Animation {
+    /// Returns the sub-animation starting at the frame with the label `label`.
+    ///
+    /// Labels mark the start of the animations of a movie clip, such as `idle`
+    /// or `attack`. The sub-animation lasts until the next labeled frame or
+    /// the end of the movie clip. If no frame has the label, `None` is
+    /// returned.
+    pub fn sub_animation(&self, label: &str) -> Option<Animation> {
+        let start = self
+            .frames
+            .iter()
+            .position(|f| f.label.as_deref() == Some(label))?;
+        let end = self.frames[start + 1..]
+            .iter()
+            .position(|f| f.label.is_some())
+            .map_or(self.frames.len(), |x| start + 1 + x);
+
+        Some(Animation {
+            clip_id: self.clip_id,
+            fps: self.fps,
+            frames: self.frames[start..end].to_vec(),
+        })
+    }
+}
+
+/// A single rendered frame of a movie clip.
+#[derive(Clone, Debug)]
+pub struct RenderedFrame {
+    /// Label of the frame, if any.
+    pub label: Option<String>,
+    /// The frame image, with premultiplied alpha.
+    pub image: RgbaImage,
+}
+
+/// A sprite placed in a frame.
+struct Placement<'a> {
+    sprite: &'a Sprite,
+    matrix: Matrix,
+    color_transform: ColorTransform,
+}
+
+/// Renders every frame of the movie clip with the ID `clip_id`.
+///
+/// The frames are composed from `sprites`, as cut by [`cut_shapes`] from the
+/// same file. Children are placed with the matrices of their frame elements,
+/// taken from the matrix bank of their movie clip. The matrices of nested
+/// movie clips are combined, and the clips play their frames in step with
+/// their parent, looping when they are shorter. Text fields are not drawn.
+///
+/// All frames share the same canvas, fitting the children of every frame.
+/// The canvas has the resolution of the largest sprite scale, so frames
+/// cut from high resolution textures are rendered in high resolution.
+///
+/// If `color_transforms` is `true`, children are also tinted and faded with
+/// the color transforms of their frame elements, which are combined like the
+/// matrices. Otherwise, they are drawn with their original colors.
+///
+/// Elements referring to a missing child are skipped, and the ones referring
+/// to a missing matrix or color transform are drawn without transforming
+/// them.
+///
+/// ## Errors
+///
+/// If the file has no movie clip with the ID `clip_id`, [`Error::UnknownClip`]
+/// is returned.
+///
+/// [`cut_shapes`]: ./fn.cut_shapes.html
+/// [`Error::UnknownClip`]: ./enum.Error.html#variant.UnknownClip
+pub fn render_movie_clip(
+    file: &ScFile,
+    sprites: &[Sprite],
+    clip_id: u16,
+    color_transforms: bool,
+) -> Result<Animation, Error> {
+    let clips: HashMap<u16, &MovieClip> = file.movie_clips.iter().map(|c| (c.id, c)).collect();
+    let sprites: HashMap<u16, &Sprite> = sprites.iter().map(|s| (s.shape_id, s)).collect();
+    let clip = *clips
+        .get(&clip_id)
+        .ok_or(Error::UnknownClip { id: clip_id })?;
+
+    let placements: Vec<Vec<Placement>> = (0..clip.frames.len())
+        .map(|frame| {
+            let mut placements = Vec::new();
+            place_frame(
+                file,
+                &clips,
+                &sprites,
+                clip,
+                frame,
+                &Matrix::default(),
+                &ColorTransform::default(),
+                0,
+                &mut placements,
+            );
+
+            placements
+        })
+        .collect();
+
+    let scale = placements
+        .iter()
+        .flatten()
+        .map(|p| p.sprite.scale.0.max(p.sprite.scale.1))
+        .fold(0.0, f32::max);
+    let scale = if scale > 0.0 { scale } else { 1.0 };
+
+    // Bounds of the canvas, in the coordinates of the movie clip.
+    let mut bounds: Option<(f32, f32, f32, f32)> = None;
+    for placement in placements.iter().flatten() {
+        let (left, top, right, bottom) = sprite_bounds(placement);
+        bounds = Some(match bounds {
+            Some(b) => (b.0.min(left), b.1.min(top), b.2.max(right), b.3.max(bottom)),
+            None => (left, top, right, bottom),
+        });
+    }
+
+    let (left, top, right, bottom) = bounds.unwrap_or((0.0, 0.0, 0.0, 0.0));
+    let (left, top) = ((left * scale).floor(), (top * scale).floor());
+    let width = ((right * scale).ceil() - left).max(1.0) as u32;
+    let height = ((bottom * scale).ceil() - top).max(1.0) as u32;
+
+    let frames = placements
+        .par_iter()
+        .zip(&clip.frames)
+        .map(|(placements, frame)| {
+            let mut image = RgbaImage::new(width, height);
+            for placement in placements {
+                draw(&mut image, placement, color_transforms, scale, left, top);
+            }
+
+            RenderedFrame {
+                label: frame.label.clone(),
+                image,
+            }
+        })
+        .collect();
+
+    Ok(Animation {
+        clip_id,
+        fps: clip.fps,
+        frames,
+    })
+}
+
+/// Collects the sprites drawn in `frame` of `clip`, placed with `matrix` and
+/// `color_transform`.
+#[allow(clippy::too_many_arguments)]
+fn place_frame<'a>(
+    file: &ScFile,
+    clips: &HashMap<u16, &MovieClip>,
+    sprites: &HashMap<u16, &'a Sprite>,
+    clip: &MovieClip,
+    frame: usize,
+    matrix: &Matrix,
+    color_transform: &ColorTransform,
+    depth: usize,
+    placements: &mut Vec<Placement<'a>>,
+) {
+    if depth >= MAX_DEPTH {
+        return;
+    }
+
+    let bank = file.matrix_banks.get(clip.matrix_bank as usize);
+    for element in &clip.frames[frame].elements {
+        let child = match clip.children.get(element.child as usize) {
+            Some(c) => c,
+            None => continue,
+        };
+
+        let element_matrix = element
+            .matrix
+            .and_then(|m| bank?.matrices.get(m as usize))
+            .copied()
+            .unwrap_or_default();
+        let matrix = matrix.multiply(&element_matrix);
+
+        let element_color_transform = element
+            .color_transform
+            .and_then(|c| bank?.color_transforms.get(c as usize))
+            .copied()
+            .unwrap_or_default();
+        let color_transform = color_transform.combine(&element_color_transform);
+
+        if let Some(sprite) = sprites.get(&child.id) {
+            placements.push(Placement {
+                sprite,
+                matrix,
+                color_transform,
+            });
+        } else if let Some(child_clip) = clips.get(&child.id) {
+            if !child_clip.frames.is_empty() {
+                place_frame(
+                    file,
+                    clips,
+                    sprites,
+                    child_clip,
+                    frame % child_clip.frames.len(),
+                    &matrix,
+                    &color_transform,
+                    depth + 1,
+                    placements,
+                );
+            }
+        }
+    }
+}
+
+/// Returns the matrix mapping pixels of the sprite image of `placement` to
+/// the coordinates of the movie clip.
+fn sprite_matrix(placement: &Placement) -> Matrix {
+    let sprite = placement.sprite;
+    let (scale_x, scale_y) = sprite.scale;
+    let (origin_x, origin_y) = (sprite.origin.0 as f32, sprite.origin.1 as f32);
+
+    placement.matrix.multiply(&Matrix {
+        a: 1.0 / scale_x,
+        b: 0.0,
+        c: 0.0,
+        d: 1.0 / scale_y,
+        tx: -origin_x / scale_x,
+        ty: -origin_y / scale_y,
+    })
+}
+
+/// Returns the left, top, right and bottom edges of the sprite image of
+/// `placement`, in the coordinates of the movie clip.
+fn sprite_bounds(placement: &Placement) -> (f32, f32, f32, f32) {
+    let m = sprite_matrix(placement);
+    let (width, height) = placement.sprite.image.dimensions();
+    let (width, height) = (width as f32, height as f32);
+
+    let mut bounds = (f32::MAX, f32::MAX, f32::MIN, f32::MIN);
+    for &(x, y) in &[(0.0, 0.0), (width, 0.0), (0.0, height), (width, height)] {
+        let (x, y) = m.transform(x, y);
+        bounds = (
+            bounds.0.min(x),
+            bounds.1.min(y),
+            bounds.2.max(x),
+            bounds.3.max(y),
+        );
+    }
+
+    bounds
+}
+
+/// Draws the sprite of `placement` on `canvas`, whose top left corner is at
+/// (`left`, `top`) in the coordinates of the movie clip scaled by `scale`.
+///
+/// The color transform of `placement` is applied if `color_transforms` is
+/// `true`.
+fn draw(
+    canvas: &mut RgbaImage,
+    placement: &Placement,
+    color_transforms: bool,
+    scale: f32,
+    left: f32,
+    top: f32,
+) {
+    let (sprite_left, sprite_top, sprite_right, sprite_bottom) = sprite_bounds(placement);
+
+    // Only the part of the canvas covered by the sprite is warped.
+    let x = ((sprite_left * scale).floor() - left).max(0.0) as u32;
+    let y = ((sprite_top * scale).floor() - top).max(0.0) as u32;
+    let right = (((sprite_right * scale).ceil() - left) as u32).min(canvas.width());
+    let bottom = (((sprite_bottom * scale).ceil() - top) as u32).min(canvas.height());
+    if right <= x || bottom <= y {
+        return;
+    }
+
+    let m = sprite_matrix(placement);
+    let (offset_x, offset_y) = (left + x as f32, top + y as f32);
+    let projection = match Projection::from_matrix([
+        m.a * scale,
+        m.c * scale,
+        m.tx * scale - offset_x,
+        m.b * scale,
+        m.d * scale,
+        m.ty * scale - offset_y,
+        0.0,
+        0.0,
+        1.0,
+    ]) {
+        Some(p) => p,
+        // The sprite is scaled to nothing.
+        None => return,
+    };
+
+    let mut warped = RgbaImage::new(right - x, bottom - y);
+    warp_into(
+        &placement.sprite.image,
+        &projection,
+        Interpolation::Bilinear,
+        Rgba([0, 0, 0, 0]),
+        &mut warped,
+    );
+
+    if color_transforms && placement.color_transform != ColorTransform::default() {
+        for pixel in warped.pixels_mut() {
+            pixel.0 = placement.color_transform.apply_premultiplied(pixel.0);
+        }
+    }
+
+    composite_over(canvas, &warped, x, y).expect("warped sprite fits the canvas");
+}
@@ -1,128 +1,167 @@
-use super::error::Error;
+use super::{
+    error::Error,
+    header::{Compression, Header},
+};
 use byteorder::{LittleEndian, ReadBytesExt};
 use lzma_rs::lzma_decompress;
+use ruzstd::StreamingDecoder;
 use std::io::{Cursor, Read};
 
 /// Wrapper for reading data from stream.
+///
+/// All reads are checked against the remaining data. Reading past the end of
+/// the stream returns [`Error::UnexpectedEof`] with the offset of the read.
+///
+/// [`Error::UnexpectedEof`]: ./error/enum.Error.html#variant.UnexpectedEof
 pub(crate) struct Reader {
     stream: Cursor<Vec<u8>>,
-    bytes_left: usize,
 }
 
 impl Reader {
     /// Create new `Reader` instance from a stream.
     pub fn new(stream: Cursor<Vec<u8>>) -> Self {
-        let bytes_left = stream.get_ref().len();
-
-        Self { stream, bytes_left }
+        Self { stream }
     }
 
     /// Bytes left in the data stream.
     pub fn len(&self) -> usize {
-        self.bytes_left
+        self.stream.get_ref().len().saturating_sub(self.offset())
     }
 
-    /// Read exact number of bytes from the stream.
-    pub fn read(&mut self, size: usize) -> Vec<u8> {
-        if size > self.bytes_left {
-            self.bytes_left = 0;
+    /// Current offset from the start of the data stream.
+    pub fn offset(&self) -> usize {
+        self.stream.position() as usize
+    }
+
+    /// Moves to the given offset from the start of the data stream.
+    pub fn seek(&mut self, offset: usize) -> Result<(), Error> {
+        if offset > self.stream.get_ref().len() {
+            return Err(Error::UnexpectedEof {
+                offset: self.offset(),
+                wanted: offset - self.offset(),
+            });
+        }
+
+        self.stream.set_position(offset as u64);
+        Ok(())
+    }
+
+    /// Returns an error if less than `wanted` bytes are left in the stream.
+    fn ensure(&self, wanted: usize) -> Result<(), Error> {
+        if wanted > self.len() {
+            Err(Error::UnexpectedEof {
+                offset: self.offset(),
+                wanted,
+            })
         } else {
-            self.bytes_left -= size;
+            Ok(())
         }
+    }
+
+    /// Read exact number of bytes from the stream.
+    pub fn read(&mut self, size: usize) -> Result<Vec<u8>, Error> {
+        self.ensure(size)?;
 
         let mut buf = vec![0; size];
-        if self.bytes_left == 0 {
-            self.stream.read_to_end(&mut buf).unwrap_or_default();
+        self.stream.read_exact(&mut buf)?;
 
-            buf
-        } else {
-            self.stream.read_exact(&mut buf).unwrap_or_default();
+        Ok(buf)
+    }
 
-            buf
-        }
+    /// Borrow exact number of bytes from the stream, without copying them.
+    pub fn read_slice(&mut self, size: usize) -> Result<&[u8], Error> {
+        self.ensure(size)?;
+
+        let start = self.offset();
+        self.stream.set_position((start + size) as u64);
+
+        Ok(&self.stream.get_ref()[start..start + size])
     }
 
-    /// Read one byte from the stream.
-    pub fn read_byte(&mut self) -> u8 {
-        if 1 > self.bytes_left {
-            self.bytes_left = 0;
-        } else {
-            self.bytes_left -= 1;
-        }
+    /// Skip exact number of bytes in the stream.
+    pub fn skip(&mut self, size: usize) -> Result<(), Error> {
+        self.ensure(size)?;
+        self.stream.set_position((self.offset() + size) as u64);
 
-        self.stream.read_u8().unwrap_or_default()
+        Ok(())
     }
 
-    /// Read an unsigned 16-bit little-endian integer from the stream.
-    pub fn read_uint16(&mut self) -> u16 {
-        if 2 > self.bytes_left {
-            self.bytes_left = 0;
-        } else {
-            self.bytes_left -= 2;
-        }
+    /// Read one byte from the stream.
+    pub fn read_byte(&mut self) -> Result<u8, Error> {
+        self.ensure(1)?;
+        Ok(self.stream.read_u8()?)
+    }
 
-        self.stream.read_u16::<LittleEndian>().unwrap_or_default()
+    /// Read an unsigned 16-bit little-endian integer from the stream.
+    pub fn read_uint16(&mut self) -> Result<u16, Error> {
+        self.ensure(2)?;
+        Ok(self.stream.read_u16::<LittleEndian>()?)
     }
 
     /// Read an unsigned 32-bit little-endian integer from the stream.
-    pub fn read_uint32(&mut self) -> u32 {
-        if 4 > self.bytes_left {
-            self.bytes_left = 0;
-        } else {
-            self.bytes_left -= 4;
-        }
-
-        self.stream.read_u32::<LittleEndian>().unwrap_or_default()
+    pub fn read_uint32(&mut self) -> Result<u32, Error> {
+        self.ensure(4)?;
+        Ok(self.stream.read_u32::<LittleEndian>()?)
     }
 
     /// Read an signed 16-bit little-endian integer from the stream.
-    pub fn read_int16(&mut self) -> i16 {
-        if 2 > self.bytes_left {
-            self.bytes_left = 0;
-        } else {
-            self.bytes_left -= 2;
-        }
-
-        self.stream.read_i16::<LittleEndian>().unwrap_or_default()
+    pub fn read_int16(&mut self) -> Result<i16, Error> {
+        self.ensure(2)?;
+        Ok(self.stream.read_i16::<LittleEndian>()?)
     }
 
     /// Read an signed 32-bit little-endian integer from the stream.
-    pub fn read_int32(&mut self) -> i32 {
-        if 4 > self.bytes_left {
-            self.bytes_left = 0;
-        } else {
-            self.bytes_left -= 4;
-        }
-
-        self.stream.read_i32::<LittleEndian>().unwrap_or_default()
+    pub fn read_int32(&mut self) -> Result<i32, Error> {
+        self.ensure(4)?;
+        Ok(self.stream.read_i32::<LittleEndian>()?)
     }
 
     /// Read `length` bytes from the stream and return the output as a `String`.
-    pub fn read_string(&mut self, length: usize) -> String {
-        if length > self.bytes_left {
-            self.bytes_left = 0;
-        } else {
-            self.bytes_left -= length;
-        }
-
-        String::from_utf8_lossy(self.read(length).as_slice()).to_string()
+    pub fn read_string(&mut self, length: usize) -> Result<String, Error> {
+        Ok(String::from_utf8_lossy(self.read(length)?.as_slice()).to_string())
     }
 }
 
-/// Decompresses `.tex_sc` or `.csv` data.
+/// Decompresses raw `_tex.sc`, `.sc` or `.csv` file data.
+///
+/// The header of the file is parsed first to find out where the compressed
+/// payload starts and which compression it uses. The parsed [`Header`] is
+/// returned along with a `Cursor` containing the decompressed data.
+///
+/// If the header is invalid or the decompression fails due to any reason,
+/// [`Error::DecompressionError`] is returned.
+///
+/// [`Header`]: ./header/struct.Header.html
+/// [`Error::DecompressionError`]: ./error/enum.Error.html#variant.DecompressionError
+pub(crate) fn decompress(raw_data: &[u8]) -> Result<(Header, Cursor<Vec<u8>>), Error> {
+    let header = Header::parse(raw_data)?;
+    let payload = header.payload(raw_data);
+
+    let decompressed = match header.compression {
+        Compression::Lzma => decompress_lzma(payload)?,
+        Compression::Zstd => decompress_zstd(payload)?,
+    };
+
+    Ok((header, decompressed))
+}
+
+/// Decompresses an LZMA payload.
 ///
 /// Before decompressing the data using LZMA decompression,
 /// four `\x00` bytes are added to `raw_data` after the eigth index.
 /// A `Cursor` containing the transformed raw data is returned.
 ///
-/// `_tex.sc` files found in Supercell's games require the header
-/// to be removed before decompression.
-///
 /// If the decompression fails due to any reason,
 /// [`Error::DecompressionError`] is returned.
 ///
 /// [`Error::DecompressionError`]: ./error/enum.Error.html#variant.DecompressionError
-pub(crate) fn decompress(raw_data: &[u8]) -> Result<Cursor<Vec<u8>>, Error> {
+fn decompress_lzma(raw_data: &[u8]) -> Result<Cursor<Vec<u8>>, Error> {
+    if raw_data.len() < 9 {
+        return Err(Error::DecompressionError(
+            "Size of file is too small".to_string(),
+        ));
+    }
+
     let data = [&raw_data[0..9], &[b'\x00'; 4], &raw_data[9..]].concat();
 
     let mut decomp: Vec<u8> = Vec::new();
@@ -133,3 +172,28 @@ pub(crate) fn decompress(raw_data: &[u8]) -> Result<Cursor<Vec<u8>>, Error> {
         )),
     }
 }
+
+/// Decompresses a Zstandard payload.
+///
+/// If the decompression fails due to any reason,
+/// [`Error::DecompressionError`] is returned.
+///
+/// [`Error::DecompressionError`]: ./error/enum.Error.html#variant.DecompressionError
+pub(crate) fn decompress_zstd(raw_data: &[u8]) -> Result<Cursor<Vec<u8>>, Error> {
+    let mut decoder = match StreamingDecoder::new(raw_data) {
+        Ok(d) => d,
+        Err(_) => {
+            return Err(Error::DecompressionError(
+                "Failed to decompress file".to_string(),
+            ))
+        }
+    };
+
+    let mut decomp: Vec<u8> = Vec::new();
+    match decoder.read_to_end(&mut decomp) {
+        Ok(_) => Ok(Cursor::new(decomp)),
+        Err(_) => Err(Error::DecompressionError(
+            "Failed to decompress file".to_string(),
+        )),
+    }
+}
@@ -72,3 +72,21 @@ fn test_all_blocking() {
         );
     }
 }
+
+#[test]
+fn test_zstd() {
+    let out_dir = Path::new("./tests/out/csv_zstd");
+
+    prepare_out_dir(out_dir);
+
+    let lzma = fs::read("./tests/data/csv/alliance_badges.csv").unwrap();
+    let zstd = fs::read("./tests/data/csv/alliance_badges_zstd.csv").unwrap();
+
+    process_csv(lzma.as_slice(), "lzma.csv", out_dir).unwrap();
+    process_csv(zstd.as_slice(), "zstd.csv", out_dir).unwrap();
+
+    assert_eq!(
+        fs::read(out_dir.join("lzma.csv")).unwrap(),
+        fs::read(out_dir.join("zstd.csv")).unwrap()
+    );
+}
@@ -0,0 +1,75 @@
+use sc_extract::{verify, Compression, Error, Header};
+use std::{fs, path::Path};
+
+#[test]
+fn test_tex_header() {
+    let path = Path::new("./tests/data/sc/background_basic_tex.sc");
+    let data = fs::read(path).unwrap();
+
+    let header = Header::parse(data.as_slice()).unwrap();
+    assert_eq!(Some(1), header.version);
+    assert_eq!(16, header.hash.len());
+    assert_eq!(Compression::Lzma, header.compression);
+    assert_eq!(26, header.payload_offset);
+    assert_eq!(data.len() - 26, header.payload_len);
+}
+
+#[test]
+fn test_csv_header() {
+    let path = Path::new("./tests/data/csv/alliance_badges.csv");
+    let data = fs::read(path).unwrap();
+
+    let header = Header::parse(data.as_slice()).unwrap();
+    assert_eq!(None, header.version);
+    assert!(header.hash.is_empty());
+    assert_eq!(Compression::Lzma, header.compression);
+    assert_eq!(0, header.payload_offset);
+}
+
+#[test]
+fn test_truncated_header() {
+    assert!(Header::parse(b"SC\x00\x00\x00\x01\x00\x00\x00\x10abc").is_err());
+    assert!(Header::parse(b"SC\x00").is_err());
+}
+
+#[test]
+fn test_zstd_header() {
+    let path = Path::new("./tests/data/sc/background_basic_zstd_tex.sc");
+    let data = fs::read(path).unwrap();
+
+    let header = Header::parse(data.as_slice()).unwrap();
+    assert_eq!(Some(3), header.version);
+    assert_eq!(Compression::Zstd, header.compression);
+    assert_eq!(26, header.payload_offset);
+}
+
+#[test]
+fn test_verify() {
+    let path = Path::new("./tests/data/sc/background_basic_zstd_tex.sc");
+    let mut data = fs::read(path).unwrap();
+
+    assert!(verify(data.as_slice()).is_ok());
+
+    // Corrupt the stored hash.
+    data[10] ^= 0xFF;
+    match verify(data.as_slice()) {
+        Err(Error::HashMismatch { .. }) => (),
+        _ => panic!("Expected a hash mismatch."),
+    }
+}
+
+#[test]
+fn test_verify_lzma() {
+    let path = Path::new("./tests/data/sc/background_basic_tex.sc");
+    let mut data = fs::read(path).unwrap();
+
+    let header = verify(data.as_slice()).unwrap();
+    assert_eq!(Compression::Lzma, header.compression);
+
+    // Corrupt the stored hash.
+    data[10] ^= 0xFF;
+    match verify(data.as_slice()) {
+        Err(Error::HashMismatch { .. }) => (),
+        _ => panic!("Expected a hash mismatch."),
+    }
+}
@@ -0,0 +1,255 @@
+mod utils;
+
+use sc_extract::{
+    model::{ColorTransform, Export, FrameElement, Matrix, MatrixBank},
+    Error, ScFile, TextureTag,
+};
+use std::fs;
+use utils::*;
+
+#[test]
+fn test_parse() {
+    // A synthetic file in the layout of game files.
+    let data = fs::read("./tests/data/sc_file/background_basic.sc").unwrap();
+    let file = ScFile::parse(data.as_slice()).unwrap();
+
+    assert_eq!(2, file.textures.len());
+    assert_eq!(TextureTag::Texture, file.textures[0].tag);
+    assert_eq!(
+        (246, 260),
+        (file.textures[1].width, file.textures[1].height)
+    );
+
+    assert_eq!(
+        vec![0, 1, 2],
+        file.shapes.iter().map(|s| s.id).collect::<Vec<_>>()
+    );
+    let region = &file.shapes[2].regions[1];
+    assert_eq!(
+        (22, 1, 4),
+        (region.tag, region.texture, region.points.len())
+    );
+    // Points are stored in twips and read in pixels.
+    assert_eq!(
+        vec![(0.0, 0.0), (40.0, 0.0), (40.0, 40.0), (0.0, 40.0)],
+        region.points
+    );
+
+    assert_eq!(
+        vec![
+            Export {
+                id: 3,
+                name: "hero_idle".to_string()
+            },
+            Export {
+                id: 2,
+                name: "hero_portrait".to_string()
+            },
+        ],
+        file.exports
+    );
+
+    let clip = &file.movie_clips[0];
+    assert_eq!((3, 24), (clip.id, clip.fps));
+    assert_eq!(Some("part".to_string()), clip.children[1].name);
+    assert_eq!(2, clip.frames.len());
+    assert_eq!(Some("idle".to_string()), clip.frames[0].label);
+    assert_eq!(
+        FrameElement {
+            child: 1,
+            matrix: Some(1),
+            color_transform: Some(0),
+        },
+        clip.frames[0].elements[1]
+    );
+    assert_eq!(None, clip.frames[1].label);
+
+    assert_eq!(
+        Matrix {
+            a: 0.5,
+            d: 0.5,
+            tx: -20.0,
+            ..Matrix::default()
+        },
+        file.matrix_banks[0].matrices[1]
+    );
+    assert_eq!(
+        ColorTransform {
+            multiply: [255, 128, 128, 128],
+            add: [0, 0, 0],
+        },
+        file.matrix_banks[0].color_transforms[0]
+    );
+}
+
+#[test]
+fn test_shape_names() {
+    let data = fs::read("./tests/data/sc_file/background_basic.sc").unwrap();
+    let names = ScFile::parse(data.as_slice()).unwrap().shape_names();
+
+    assert_eq!(3, names.len());
+    assert_eq!("hero_idle_0", names[&0]);
+    assert_eq!("hero_idle_1", names[&1]);
+    assert_eq!("hero_portrait", names[&2]);
+}
+
+#[test]
+fn test_text_field() {
+    let mut body = vec![7, 0, 4];
+    body.extend_from_slice(b"Font");
+    body.extend_from_slice(&0xFF00_FF00u32.to_le_bytes());
+    body.extend_from_slice(&[1, 0, 1, 0, 2, 24]);
+    for edge in &[-10i16, -5, 10, 5] {
+        body.extend_from_slice(&edge.to_le_bytes());
+    }
+    body.extend_from_slice(&[0, 255]);
+
+    let file = ScFile::parse(&sc_file([0, 0, 0, 1, 0, 0], &[(7, body)])).unwrap();
+    let text_field = &file.text_fields[0];
+    assert_eq!(7, text_field.id);
+    assert_eq!(Some("Font".to_string()), text_field.font_name);
+    assert_eq!(0xFF00_FF00, text_field.color);
+    assert!(text_field.bold && !text_field.italic && text_field.multiline);
+    assert_eq!((2, 24), (text_field.align, text_field.font_size));
+    assert_eq!(
+        (-10, -5, 10, 5),
+        (
+            text_field.left,
+            text_field.top,
+            text_field.right,
+            text_field.bottom
+        )
+    );
+    assert_eq!(None, text_field.text);
+}
+
+#[test]
+fn test_bad_region() {
+    // A shape with a region cut out of texture 1, but the file has one texture.
+    let mut body = vec![0, 0, 1, 0, 22];
+    body.extend_from_slice(&2u32.to_le_bytes());
+    body.extend_from_slice(&[1, 3]);
+
+    match ScFile::parse(&sc_file([1, 0, 1, 0, 0, 0], &[(2, body)])) {
+        Err(Error::BadTag { tag: 22, offset }) => assert_eq!(28, offset),
+        _ => panic!("Expected a bad tag error."),
+    }
+}
+
+#[test]
+fn test_huge_movie_clip() {
+    // A movie clip claiming far more frame elements than the file holds.
+    let mut clip = vec![4, 0, 30, 255, 255];
+    clip.extend_from_slice(&i32::MAX.to_le_bytes());
+
+    assert!(ScFile::parse(&sc_file([0, 1, 0, 0, 0, 0], &[(12, clip)])).is_err());
+}
+
+#[test]
+fn test_matrix_banks() {
+    let mut matrix = Vec::new();
+    for value in &[1024i32, 0, 0, 2048, 200, -100] {
+        matrix.extend_from_slice(&value.to_le_bytes());
+    }
+    let mut precise_matrix = Vec::new();
+    for value in &[65535i32, 0, 65535, 65535, 0, 0] {
+        precise_matrix.extend_from_slice(&value.to_le_bytes());
+    }
+
+    // A movie clip without elements, using the second bank.
+    let mut clip = vec![4, 0, 30, 0, 0, 0, 0, 0, 0, 0, 0];
+    clip.extend_from_slice(&[41, 1, 0, 0, 0, 1]);
+    clip.extend_from_slice(&[0, 0, 0, 0, 0]);
+
+    let file = ScFile::parse(&sc_file(
+        [0, 1, 0, 0, 1, 1],
+        &[
+            (8, matrix),
+            (9, vec![0, 0, 0, 255, 255, 255, 255]),
+            (42, vec![1, 0, 0, 0]),
+            (36, precise_matrix),
+            (12, clip),
+        ],
+    ))
+    .unwrap();
+
+    assert_eq!(
+        vec![
+            MatrixBank {
+                matrices: vec![Matrix {
+                    d: 2.0,
+                    tx: 10.0,
+                    ty: -5.0,
+                    ..Matrix::default()
+                }],
+                color_transforms: vec![ColorTransform::default()],
+            },
+            MatrixBank {
+                matrices: vec![Matrix {
+                    c: 1.0,
+                    ..Matrix::default()
+                }],
+                color_transforms: Vec::new(),
+            },
+        ],
+        file.matrix_banks
+    );
+    assert_eq!(1, file.movie_clips[0].matrix_bank);
+}
+
+#[test]
+fn test_matrix_multiply() {
+    let parent = Matrix {
+        a: 2.0,
+        tx: 5.0,
+        ..Matrix::default()
+    };
+    let child = Matrix {
+        c: 1.0,
+        ty: 3.0,
+        ..Matrix::default()
+    };
+
+    // The child shears the point, then the parent scales and moves it.
+    let (x, y) = child.transform(1.0, 2.0);
+    assert_eq!((3.0, 5.0), (x, y));
+    assert_eq!((11.0, 5.0), parent.transform(x, y));
+    assert_eq!((11.0, 5.0), parent.multiply(&child).transform(1.0, 2.0));
+}
+
+#[test]
+fn test_color_transform() {
+    let fade = ColorTransform {
+        multiply: [255, 255, 255, 128],
+        add: [0, 0, 0],
+    };
+    let tint = ColorTransform {
+        multiply: [255, 0, 0, 255],
+        add: [0, 0, 255],
+    };
+
+    // Colors are premultiplied, so fading scales all channels.
+    assert_eq!(
+        [128, 64, 0, 128],
+        fade.apply_premultiplied([255, 128, 0, 255])
+    );
+    assert_eq!(
+        [255, 0, 255, 255],
+        tint.apply_premultiplied([255, 128, 0, 255])
+    );
+    assert_eq!([0, 0, 0, 0], tint.apply_premultiplied([0, 0, 0, 0]));
+
+    // The tint is applied first, then the fade.
+    let combined = fade.combine(&tint);
+    assert_eq!(
+        ColorTransform {
+            multiply: [255, 0, 0, 128],
+            add: [0, 0, 255],
+        },
+        combined
+    );
+    assert_eq!(
+        fade.apply_premultiplied(tint.apply_premultiplied([255, 128, 0, 255])),
+        combined.apply_premultiplied([255, 128, 0, 255])
+    );
+}
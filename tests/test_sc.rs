@@ -0,0 +1,634 @@
+mod utils;
+
+use image::{
+    codecs::{gif::GifDecoder, png::PngDecoder, webp::WebPDecoder},
+    imageops::{self, FilterType},
+    AnimationDecoder, RgbaImage,
+};
+use sc_extract::{
+    cut_shapes, cut_sprites, decode_tex, process_sc, process_tex, render_movie_clip, Animation,
+    AnimationFormat, Error, Event, ExportOptions, RenderedFrame, ScFile, SpriteFallback,
+    TextureVariant,
+};
+use std::{
+    fs::{self, File},
+    path::Path,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use utils::*;
+
+#[test]
+fn test_single() {
+    let tex_path = Path::new("./tests/data/sc/background_basic_tex.sc");
+    let path = Path::new("./tests/data/sc_file/background_basic.sc");
+    let png_dir = Path::new("./tests/out/sc_file/png");
+    let out_dir = Path::new("./tests/out/sc_file/sprites");
+
+    prepare_out_dir(png_dir);
+    prepare_out_dir(out_dir);
+
+    let tex_data = fs::read(tex_path).unwrap();
+    process_tex(
+        tex_data.as_slice(),
+        get_file_name(tex_path),
+        png_dir,
+        tex_path.parent().unwrap(),
+        &ExportOptions::default(),
+        &(),
+    )
+    .unwrap();
+
+    let data = fs::read(path).unwrap();
+    assert!(process_sc(
+        data.as_slice(),
+        get_file_name(path),
+        out_dir,
+        png_dir,
+        &ExportOptions::default(),
+        &()
+    )
+    .is_ok());
+    assert!(out_dir.join("hero_idle_0.png").exists());
+    assert!(out_dir.join("hero_idle_1.png").exists());
+    assert!(out_dir.join("hero_portrait.png").exists());
+
+    let options = ExportOptions {
+        numbered_sprites: true,
+        ..ExportOptions::default()
+    };
+    process_sc(
+        data.as_slice(),
+        get_file_name(path),
+        out_dir,
+        png_dir,
+        &options,
+        &(),
+    )
+    .unwrap();
+    assert!(out_dir.join("background_basic_sprite_0.png").exists());
+}
+
+#[test]
+fn test_sprite_fallback() {
+    let out_dir = Path::new("./tests/out/sc_file/fallback");
+
+    prepare_out_dir(out_dir);
+    RgbaImage::new(4, 4)
+        .save(out_dir.join("unnamed_tex.png"))
+        .unwrap();
+
+    // A 4x4 texture and a shape covering it, without exports.
+    let mut data = vec![1, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    data.extend_from_slice(&[1, 5, 0, 0, 0, 0, 4, 0, 4, 0]);
+    data.extend_from_slice(&[2, 64, 0, 0, 0, 7, 0, 1, 0]);
+    data.extend_from_slice(&[22, 50, 0, 0, 0, 0, 4]);
+    for point in &[(0i32, 0i32), (80, 0), (80, 80), (0, 80)] {
+        data.extend_from_slice(&point.0.to_le_bytes());
+        data.extend_from_slice(&point.1.to_le_bytes());
+    }
+    for uv in &[(0u16, 0u16), (65535, 0), (65535, 65535), (0, 65535)] {
+        data.extend_from_slice(&uv.0.to_le_bytes());
+        data.extend_from_slice(&uv.1.to_le_bytes());
+    }
+    data.extend_from_slice(&[0; 10]);
+    let data = container(&data);
+
+    let options = ExportOptions {
+        sprite_fallback: SpriteFallback::ShapeId,
+        ..ExportOptions::default()
+    };
+    process_sc(
+        data.as_slice(),
+        "unnamed.sc",
+        out_dir,
+        out_dir,
+        &options,
+        &(),
+    )
+    .unwrap();
+    assert!(out_dir.join("unnamed_shape_7.png").exists());
+
+    let options = ExportOptions {
+        sprite_fallback: SpriteFallback::Skip,
+        ..ExportOptions::default()
+    };
+    let written = AtomicUsize::new(0);
+    let observer = |event: &Event| {
+        if let Event::SpriteWritten { .. } = event {
+            written.fetch_add(1, Ordering::SeqCst);
+        }
+    };
+    process_sc(
+        data.as_slice(),
+        "unnamed.sc",
+        out_dir,
+        out_dir,
+        &options,
+        &observer,
+    )
+    .unwrap();
+    assert_eq!(0, written.into_inner());
+}
+
+#[test]
+fn test_sprite_name_collisions() {
+    let out_dir = Path::new("./tests/out/sc_file/collisions");
+
+    prepare_out_dir(out_dir);
+    RgbaImage::new(4, 4)
+        .save(out_dir.join("collisions_tex.png"))
+        .unwrap();
+
+    // Three shapes exported as `a`, `a` and `a_1`.
+    let mut data = vec![3, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 3, 0];
+    data.extend_from_slice(&[0, 0, 1, 0, 2, 0]);
+    data.extend_from_slice(&[1, b'a', 1, b'a', 3, b'a', b'_', b'1']);
+    data.extend_from_slice(&[1, 5, 0, 0, 0, 0, 4, 0, 4, 0]);
+    for id in 0..3 {
+        data.extend_from_slice(&[2, 64, 0, 0, 0, id, 0, 1, 0]);
+        data.extend_from_slice(&[22, 50, 0, 0, 0, 0, 4]);
+        for point in &[(0i32, 0i32), (80, 0), (80, 80), (0, 80)] {
+            data.extend_from_slice(&point.0.to_le_bytes());
+            data.extend_from_slice(&point.1.to_le_bytes());
+        }
+        for uv in &[(0u16, 0u16), (65535, 0), (65535, 65535), (0, 65535)] {
+            data.extend_from_slice(&uv.0.to_le_bytes());
+            data.extend_from_slice(&uv.1.to_le_bytes());
+        }
+        data.extend_from_slice(&[0; 5]);
+    }
+    data.extend_from_slice(&[0; 5]);
+    let data = container(&data);
+
+    let written = AtomicUsize::new(0);
+    let observer = |event: &Event| {
+        if let Event::SpriteWritten { .. } = event {
+            written.fetch_add(1, Ordering::SeqCst);
+        }
+    };
+    process_sc(
+        data.as_slice(),
+        "collisions.sc",
+        out_dir,
+        out_dir,
+        &ExportOptions::default(),
+        &observer,
+    )
+    .unwrap();
+
+    assert_eq!(3, written.into_inner());
+    for name in &["a", "a_1", "a_1_2"] {
+        assert!(out_dir.join(format!("{}.png", name)).exists());
+    }
+}
+
+#[test]
+fn test_truncated() {
+    let out_dir = Path::new("./tests/out/sc_file/truncated");
+
+    prepare_out_dir(out_dir);
+
+    // One shape and one movie clip, but the texture count is missing.
+    let data = container(&[1, 0, 1, 0]);
+
+    match process_sc(
+        data.as_slice(),
+        "truncated.sc",
+        out_dir,
+        out_dir,
+        &ExportOptions::default(),
+        &(),
+    ) {
+        Err(Error::UnexpectedEof { offset, wanted }) => {
+            assert_eq!(4, offset);
+            assert_eq!(2, wanted);
+        }
+        _ => panic!("Expected an unexpected EOF error."),
+    }
+}
+
+#[test]
+fn test_missing_texture() {
+    let path = Path::new("./tests/data/sc_file/background_basic.sc");
+    let out_dir = Path::new("./tests/out/sc_file/missing");
+
+    prepare_out_dir(out_dir);
+
+    let data = fs::read(path).unwrap();
+    match process_sc(
+        data.as_slice(),
+        get_file_name(path),
+        out_dir,
+        out_dir,
+        &ExportOptions::default(),
+        &(),
+    ) {
+        Err(Error::MissingTexture { path }) => {
+            assert_eq!(out_dir.join("background_basic_tex.png"), path)
+        }
+        _ => panic!("Expected a missing texture error."),
+    }
+}
+
+#[test]
+fn test_cut_sprites() {
+    let tex_data = fs::read("./tests/data/sc/background_basic_tex.sc").unwrap();
+    let data = fs::read("./tests/data/sc_file/background_basic.sc").unwrap();
+
+    let sheets: Vec<_> = decode_tex(tex_data.as_slice(), None, &())
+        .unwrap()
+        .into_iter()
+        .map(|t| t.image)
+        .collect();
+
+    let sprites = cut_sprites(data.as_slice(), &sheets, &()).unwrap();
+    assert_eq!(3, sprites.len());
+    assert_eq!(
+        vec![0, 1, 2],
+        sprites.iter().map(|s| s.shape_id).collect::<Vec<_>>()
+    );
+
+    match cut_sprites(data.as_slice(), &sheets[..1], &()) {
+        Err(Error::MissingSheet { index }) => assert_eq!(1, index),
+        _ => panic!("Expected a missing sheet error."),
+    }
+}
+
+#[test]
+fn test_region_order() {
+    // Regions at the same place in the shape, cut out of the red left half
+    // and the blue right half of a texture in turn, ending with blue.
+    let mut shape = vec![7, 0, 64, 0];
+    for x in [0u16, 4].iter().cycle().take(64) {
+        shape.extend_from_slice(&[17, 50, 0, 0, 0, 0, 4]);
+        for point in &[(0i32, 0i32), (80, 0), (80, 80), (0, 80)] {
+            shape.extend_from_slice(&point.0.to_le_bytes());
+            shape.extend_from_slice(&point.1.to_le_bytes());
+        }
+        for uv in &[(*x, 0u16), (x + 4, 0), (x + 4, 4), (*x, 4)] {
+            shape.extend_from_slice(&uv.0.to_le_bytes());
+            shape.extend_from_slice(&uv.1.to_le_bytes());
+        }
+    }
+    shape.extend_from_slice(&[0; 5]);
+
+    let data = sc_file([1, 0, 1, 0, 0, 0], &[(1, vec![0, 8, 0, 4, 0]), (2, shape)]);
+    let sheet = RgbaImage::from_fn(8, 4, |x, _| {
+        if x < 4 {
+            image::Rgba([255, 0, 0, 255])
+        } else {
+            image::Rgba([0, 0, 255, 255])
+        }
+    });
+
+    // The last region is drawn on top, every time.
+    for _ in 0..20 {
+        let sprites = cut_sprites(data.as_slice(), std::slice::from_ref(&sheet), &()).unwrap();
+        let image = &sprites[0].image;
+        assert_eq!(
+            [0, 0, 255, 255],
+            image.get_pixel(image.width() / 2, image.height() / 2).0
+        );
+    }
+}
+
+#[test]
+fn test_low_res() {
+    let tex_data = fs::read("./tests/data/sc/background_basic_tex.sc").unwrap();
+    let data = fs::read("./tests/data/sc_file/background_basic.sc").unwrap();
+    let out_dir = Path::new("./tests/out/sc_file/lowres");
+
+    prepare_out_dir(out_dir);
+
+    let sheets: Vec<_> = decode_tex(tex_data.as_slice(), None, &())
+        .unwrap()
+        .into_iter()
+        .map(|t| t.image)
+        .collect();
+    let low_res: Vec<_> = sheets
+        .iter()
+        .map(|s| imageops::resize(s, s.width() / 2, s.height() / 2, FilterType::Nearest))
+        .collect();
+
+    let sprites = cut_sprites(data.as_slice(), &sheets, &()).unwrap();
+    let low_res_sprites = cut_sprites(data.as_slice(), &low_res, &()).unwrap();
+    for (sprite, low_res_sprite) in sprites.iter().zip(&low_res_sprites) {
+        let (width, height) = sprite.image.dimensions();
+        let (low_width, low_height) = low_res_sprite.image.dimensions();
+        assert!((width as i32 / 2 - low_width as i32).abs() <= 2);
+        assert!((height as i32 / 2 - low_height as i32).abs() <= 2);
+    }
+
+    // Only the low resolution variant is present, so it is picked.
+    for (i, sheet) in low_res.iter().enumerate() {
+        let name = format!("background_basic_lowres_tex{}.png", "_".repeat(i));
+        sheet.save(out_dir.join(name)).unwrap();
+    }
+    process_sc(
+        data.as_slice(),
+        "background_basic.sc",
+        out_dir,
+        out_dir,
+        &ExportOptions::default(),
+        &(),
+    )
+    .unwrap();
+
+    let options = ExportOptions {
+        texture_variant: Some(TextureVariant::HighRes),
+        ..ExportOptions::default()
+    };
+    match process_sc(
+        data.as_slice(),
+        "background_basic.sc",
+        out_dir,
+        out_dir,
+        &options,
+        &(),
+    ) {
+        Err(Error::MissingTexture { path }) => {
+            assert_eq!(out_dir.join("background_basic_highres_tex.png"), path)
+        }
+        _ => panic!("Expected a missing texture error."),
+    }
+}
+
+#[test]
+fn test_render_movie_clip() {
+    let tex_data = fs::read("./tests/data/sc/background_basic_tex.sc").unwrap();
+    let data = fs::read("./tests/data/sc_file/background_basic.sc").unwrap();
+
+    let sheets: Vec<_> = decode_tex(tex_data.as_slice(), None, &())
+        .unwrap()
+        .into_iter()
+        .map(|t| t.image)
+        .collect();
+    let file = ScFile::parse(data.as_slice()).unwrap();
+    let sprites = cut_shapes(&file, &sheets, &()).unwrap();
+
+    let animation = render_movie_clip(&file, &sprites, 3, true).unwrap();
+    assert_eq!(3, animation.clip_id);
+    assert_eq!(24, animation.fps);
+    assert_eq!(2, animation.frames.len());
+    assert_eq!(Some("idle"), animation.frames[0].label.as_deref());
+    assert_eq!(None, animation.frames[1].label);
+
+    // The first frame moves the shape by (10, 5) and the second one does not,
+    // so the canvas fits both.
+    let (width, height) = animation.frames[0].image.dimensions();
+    assert_eq!((width, height), animation.frames[1].image.dimensions());
+    assert_eq!(sprites[0].image.width() + 10, width);
+    assert_eq!(sprites[0].image.height() + 5, height);
+    assert_eq!(
+        0,
+        animation.frames[1].image.get_pixel(width - 1, height - 1)[3]
+    );
+    assert_ne!(
+        0,
+        animation.frames[0].image.get_pixel(width - 2, height - 2)[3]
+    );
+
+    // Away from the second child, the first frame shows the first child
+    // 10 pixels right and 5 pixels down of where the second frame does.
+    let mut moved = false;
+    for y in 30..90 {
+        for x in 45..90 {
+            let pixel = animation.frames[1].image.get_pixel(x, y);
+            assert_eq!(pixel, animation.frames[0].image.get_pixel(x + 10, y + 5));
+            moved |= pixel != animation.frames[0].image.get_pixel(x, y);
+        }
+    }
+    assert!(moved);
+
+    // Only the first frame has a color transform, tinting the second child.
+    let untinted = render_movie_clip(&file, &sprites, 3, false).unwrap();
+    assert_ne!(untinted.frames[0].image, animation.frames[0].image);
+    assert_eq!(untinted.frames[1].image, animation.frames[1].image);
+
+    match render_movie_clip(&file, &sprites, 0, true) {
+        Err(Error::UnknownClip { id }) => assert_eq!(0, id),
+        _ => panic!("Expected an unknown clip error."),
+    }
+}
+
+#[test]
+fn test_movie_clip_frames() {
+    let tex_path = Path::new("./tests/data/sc/background_basic_tex.sc");
+    let path = Path::new("./tests/data/sc_file/background_basic.sc");
+    let out_dir = Path::new("./tests/out/sc_file/movie_clips");
+
+    prepare_out_dir(out_dir);
+
+    let tex_data = fs::read(tex_path).unwrap();
+    process_tex(
+        tex_data.as_slice(),
+        get_file_name(tex_path),
+        out_dir,
+        tex_path.parent().unwrap(),
+        &ExportOptions::default(),
+        &(),
+    )
+    .unwrap();
+
+    let options = ExportOptions {
+        movie_clips: true,
+        ..ExportOptions::default()
+    };
+    let written = AtomicUsize::new(0);
+    let observer = |event: &Event| {
+        if let Event::FrameWritten { clip_id, .. } = event {
+            assert_eq!(3, *clip_id);
+            written.fetch_add(1, Ordering::SeqCst);
+        }
+    };
+    let data = fs::read(path).unwrap();
+    process_sc(
+        data.as_slice(),
+        get_file_name(path),
+        out_dir,
+        out_dir,
+        &options,
+        &observer,
+    )
+    .unwrap();
+
+    assert_eq!(2, written.into_inner());
+    assert!(out_dir.join("hero_idle/hero_idle_0.png").exists());
+    assert!(out_dir.join("hero_idle/hero_idle_1.png").exists());
+}
+
+#[test]
+fn test_animations() {
+    let tex_path = Path::new("./tests/data/sc/background_basic_tex.sc");
+    let path = Path::new("./tests/data/sc_file/background_basic.sc");
+    let out_dir = Path::new("./tests/out/sc_file/animations");
+
+    prepare_out_dir(out_dir);
+
+    let tex_data = fs::read(tex_path).unwrap();
+    process_tex(
+        tex_data.as_slice(),
+        get_file_name(tex_path),
+        out_dir,
+        tex_path.parent().unwrap(),
+        &ExportOptions::default(),
+        &(),
+    )
+    .unwrap();
+
+    let data = fs::read(path).unwrap();
+    let frame_counts = |format, label: Option<&str>| {
+        let options = ExportOptions {
+            animations: true,
+            animation_format: format,
+            frame_label: label.map(str::to_string),
+            ..ExportOptions::default()
+        };
+        process_sc(
+            data.as_slice(),
+            get_file_name(path),
+            out_dir,
+            out_dir,
+            &options,
+            &(),
+        )
+        .unwrap();
+
+        let name = match label {
+            Some(l) => format!("hero_idle/hero_idle_{}.{}", l, format.extension()),
+            None => format!("hero_idle/hero_idle.{}", format.extension()),
+        };
+        let file = File::open(out_dir.join(name)).unwrap();
+        let frames = match format {
+            AnimationFormat::Apng => PngDecoder::new(file).unwrap().apng().into_frames(),
+            AnimationFormat::Gif => GifDecoder::new(file).unwrap().into_frames(),
+            AnimationFormat::WebP => WebPDecoder::new(file).unwrap().into_frames(),
+        };
+
+        let frames = frames.collect_frames().unwrap();
+        for frame in &frames {
+            // One frame lasts 1/24 seconds.
+            let (numer, denom) = frame.delay().numer_denom_ms();
+            assert!((numer as f32 / denom as f32 - 41.7).abs() < 10.0);
+        }
+
+        frames.len()
+    };
+
+    for &format in &[
+        AnimationFormat::Apng,
+        AnimationFormat::Gif,
+        AnimationFormat::WebP,
+    ] {
+        assert_eq!(2, frame_counts(format, None));
+        assert_eq!(2, frame_counts(format, Some("idle")));
+    }
+
+    // Movie clips without the label are skipped.
+    let options = ExportOptions {
+        animations: true,
+        frame_label: Some("attack".to_string()),
+        ..ExportOptions::default()
+    };
+    process_sc(
+        data.as_slice(),
+        get_file_name(path),
+        out_dir,
+        out_dir,
+        &options,
+        &(),
+    )
+    .unwrap();
+    assert!(!out_dir.join("hero_idle/hero_idle_attack.png").exists());
+}
+
+#[test]
+fn test_sub_animation() {
+    let labels = [Some("idle"), None, Some("attack"), None, None];
+    let animation = Animation {
+        clip_id: 0,
+        fps: 30,
+        frames: labels
+            .iter()
+            .map(|label| RenderedFrame {
+                label: label.map(str::to_string),
+                image: RgbaImage::new(1, 1),
+            })
+            .collect(),
+    };
+
+    let idle = animation.sub_animation("idle").unwrap();
+    assert_eq!(2, idle.frames.len());
+    assert_eq!(30, idle.fps);
+
+    let attack = animation.sub_animation("attack").unwrap();
+    assert_eq!(3, attack.frames.len());
+    assert_eq!(Some("attack"), attack.frames[0].label.as_deref());
+
+    assert!(animation.sub_animation("run").is_none());
+}
+
+#[test]
+fn test_render_matrix_bank() {
+    // A 4x4 shape covering a whole texture, with points in twips.
+    let mut shape = vec![7, 0, 1, 0, 22, 50, 0, 0, 0, 0, 4];
+    for point in &[(0i32, 0i32), (80, 0), (80, 80), (0, 80)] {
+        shape.extend_from_slice(&point.0.to_le_bytes());
+        shape.extend_from_slice(&point.1.to_le_bytes());
+    }
+    for uv in &[(0u16, 0u16), (65535, 0), (65535, 65535), (0, 65535)] {
+        shape.extend_from_slice(&uv.0.to_le_bytes());
+        shape.extend_from_slice(&uv.1.to_le_bytes());
+    }
+    shape.extend_from_slice(&[0; 5]);
+
+    let mut identity = Vec::new();
+    for value in &[1024i32, 0, 0, 1024, 0, 0] {
+        identity.extend_from_slice(&value.to_le_bytes());
+    }
+    let mut shear = Vec::new();
+    for value in &[65535i32, 0, 65535, 65535, 0, 0] {
+        shear.extend_from_slice(&value.to_le_bytes());
+    }
+
+    // A movie clip placing the shape with the first matrix of a bank.
+    let clip = |bank: u8| {
+        let mut clip = vec![8, 0, 24, 1, 0, 1, 0, 0, 0, 0, 0, 0, 0, 255, 255];
+        clip.extend_from_slice(&[1, 0, 7, 0, 0, 255]);
+        clip.extend_from_slice(&[41, 1, 0, 0, 0, bank]);
+        clip.extend_from_slice(&[11, 3, 0, 0, 0, 1, 0, 255]);
+        clip.extend_from_slice(&[0; 5]);
+        clip
+    };
+
+    let mut sheet = RgbaImage::new(4, 4);
+    for pixel in sheet.pixels_mut() {
+        *pixel = image::Rgba([255, 255, 255, 255]);
+    }
+
+    let mut widths = Vec::new();
+    for bank in 0..2 {
+        let data = sc_file(
+            [1, 1, 1, 0, 1, 0],
+            &[
+                (1, vec![0, 4, 0, 4, 0]),
+                (2, shape.clone()),
+                (8, identity.clone()),
+                (42, vec![1, 0, 0, 0]),
+                (36, shear.clone()),
+                (12, clip(bank)),
+            ],
+        );
+        let file = ScFile::parse(data.as_slice()).unwrap();
+        let sprites = cut_shapes(&file, &[sheet.clone()], &()).unwrap();
+        let animation = render_movie_clip(&file, &sprites, 8, true).unwrap();
+
+        let (width, height) = animation.frames[0].image.dimensions();
+        assert_eq!(sprites[0].image.height(), height);
+        widths.push(width);
+    }
+
+    // The shear of the second bank moves the bottom of the sprite right by
+    // its height.
+    assert_eq!(widths[0] * 2, widths[1]);
+}
@@ -1,13 +1,22 @@
 mod utils;
 
+use image::RgbaImage;
 use rayon::prelude::*;
-use sc_extract::process_tex;
-use std::{fs, path::Path};
+use sc_extract::{
+    decode_tex, premultiply, process_tex, unpremultiply, Error, Event, ExportOptions, Filter,
+    OutputFormat, PngCompression, TextureTag,
+};
+use std::{
+    fs,
+    path::Path,
+    sync::atomic::{AtomicUsize, Ordering},
+};
 use utils::*;
 
 #[test]
 fn test_single() {
-    let path = Path::new("./tests/data/sc/background_basic_tex.sc");
+    let dir = Path::new("./tests/data/sc");
+    let path = dir.join("background_basic_tex.sc");
     let data = fs::read(&path).unwrap();
     let out_dir = Path::new("./tests/out/sc");
 
@@ -15,7 +24,15 @@ fn test_single() {
 
     assert_eq!(
         true,
-        process_tex(data.as_slice(), get_file_name(path), &out_dir, true).is_ok()
+        process_tex(
+            data.as_slice(),
+            get_file_name(&path),
+            &out_dir,
+            dir,
+            &ExportOptions::default(),
+            &()
+        )
+        .is_ok()
     );
 }
 
@@ -41,7 +58,15 @@ fn test_all_parallel() {
         let data = fs::read(&path).unwrap();
         assert_eq!(
             true,
-            process_tex(data.as_slice(), get_file_name(&path), &out_dir, true).is_ok()
+            process_tex(
+                data.as_slice(),
+                get_file_name(&path),
+                &out_dir,
+                dir,
+                &ExportOptions::default(),
+                &()
+            )
+            .is_ok()
         );
     });
 }
@@ -68,7 +93,340 @@ fn test_all_blocking() {
         let data = fs::read(&path).unwrap();
         assert_eq!(
             true,
-            process_tex(data.as_slice(), get_file_name(&path), &out_dir, false).is_ok()
+            process_tex(
+                data.as_slice(),
+                get_file_name(&path),
+                &out_dir,
+                dir,
+                &ExportOptions::default(),
+                &()
+            )
+            .is_ok()
         );
     }
 }
+
+#[test]
+fn test_decode() {
+    let path = Path::new("./tests/data/sc/background_basic_tex.sc");
+    let data = fs::read(path).unwrap();
+
+    let textures = decode_tex(data.as_slice(), None, &()).unwrap();
+    assert_eq!(2, textures.len());
+
+    assert_eq!(TextureTag::Texture, textures[0].tag);
+    assert_eq!(Filter::Linear, textures[0].filter);
+    assert_eq!(0, textures[0].pixel_format);
+    assert_eq!((1024, 1152), textures[0].image.dimensions());
+
+    assert_eq!(6, textures[1].pixel_format);
+    assert_eq!((246, 260), (textures[1].width, textures[1].height));
+}
+
+#[test]
+fn test_events() {
+    let path = Path::new("./tests/data/sc/background_basic_tex.sc");
+    let data = fs::read(path).unwrap();
+
+    let started = AtomicUsize::new(0);
+    let decoded = AtomicUsize::new(0);
+    let observer = |event: &Event| match event {
+        Event::TextureStarted { .. } => {
+            started.fetch_add(1, Ordering::SeqCst);
+        }
+        Event::TextureDecoded { .. } => {
+            decoded.fetch_add(1, Ordering::SeqCst);
+        }
+        _ => (),
+    };
+
+    decode_tex(data.as_slice(), None, &observer).unwrap();
+    assert_eq!(2, started.into_inner());
+    assert_eq!(2, decoded.into_inner());
+}
+
+#[test]
+fn test_unknown_pixel() {
+    // Textures of unknown sub-types, including compressed ones outside of KTX
+    // containers, followed by a 1x1 RGBA texture.
+    let mut data = Vec::new();
+    for sub_type in 11..17 {
+        data.extend_from_slice(&[1, 9, 0, 0, 0, sub_type, 1, 0, 1, 0, 0, 0, 0, 0]);
+    }
+    data.extend_from_slice(&[1, 9, 0, 0, 0, 0, 1, 0, 1, 0, 1, 2, 3, 4]);
+
+    let unknown = AtomicUsize::new(0);
+    let observer = |event: &Event| {
+        if let Event::Warning(Error::UnknownPixel { pixel_type, .. }) = event {
+            assert!((11..17).contains(pixel_type));
+            unknown.fetch_add(1, Ordering::SeqCst);
+        }
+    };
+
+    let data = container(&data);
+    let textures = decode_tex(data.as_slice(), None, &observer).unwrap();
+    assert_eq!(6, unknown.into_inner());
+    assert_eq!(1, textures.len());
+    assert_eq!([1, 2, 3, 4], textures[0].image.get_pixel(0, 0).0);
+}
+
+#[test]
+fn test_tag_overrun() {
+    // A 2x2 RGBA texture whose tag claims to hold a single pixel.
+    let mut data = vec![1, 9, 0, 0, 0, 0, 2, 0, 2, 0];
+    data.extend_from_slice(&[255; 16]);
+
+    let data = container(&data);
+    match decode_tex(data.as_slice(), None, &()) {
+        Err(Error::BadTag { tag: 1, offset: 0 }) => (),
+        _ => panic!("Expected a bad tag error."),
+    }
+}
+
+#[test]
+fn test_etc() {
+    // ETC1, individual mode, all pixels use the first modifier (+2), followed
+    // by data that is skipped.
+    let mut etc1 = ktx_texture(&ktx(0x8D64, &[&[0xF0, 0x0F, 0x88, 0, 0, 0, 0, 0]]));
+    etc1.extend_from_slice(&[1, 2, 3, 4]);
+    let size = etc1.len() as u32 - 5;
+    etc1[1..5].copy_from_slice(&size.to_le_bytes());
+    // ETC2 RGBA, differential mode color and a constant alpha of 202.
+    let etc2 = ktx_texture(&ktx(
+        0x9278,
+        &[&[
+            0xC8, 0x10, 0x92, 0x49, 0x24, 0x92, 0x49, 0x24, 0x80, 0x40, 0, 0x02, 0, 0, 0, 0,
+        ]],
+    ));
+
+    let data = container(&[etc1, etc2].concat());
+    let textures = decode_tex(data.as_slice(), None, &()).unwrap();
+    assert_eq!(2, textures.len());
+
+    let etc1 = &textures[0].image;
+    assert_eq!([255, 2, 138, 255], etc1.get_pixel(1, 3).0);
+    assert_eq!([2, 255, 138, 255], etc1.get_pixel(2, 0).0);
+
+    let etc2 = &textures[1].image;
+    assert!(etc2.pixels().all(|p| p.0 == [134, 68, 2, 202]));
+}
+
+#[test]
+fn test_astc() {
+    // 4x4 void-extent block with a constant color.
+    let constant = ktx_texture(&ktx(
+        0x93B0,
+        &[&[
+            0xFC, 0xFD, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00, 0x80, 0x00, 0x40, 0xFF, 0xFF,
+            0xFF, 0xFF,
+        ]],
+    ));
+    // 4x4 block with black and white RGBA endpoints and weights 0, 1, 2 and
+    // 3 out of 3 in every row.
+    let gradient = ktx_texture(&ktx(
+        0x93B0,
+        &[&[
+            0x42, 0x80, 0x01, 0xFE, 0x01, 0xFE, 0x01, 0xFE, 0xFF, 0xFF, 0x01, 0x00, 0x27, 0x27,
+            0x27, 0x27,
+        ]],
+    ));
+
+    let data = container(&[constant, gradient].concat());
+    let textures = decode_tex(data.as_slice(), None, &()).unwrap();
+    assert_eq!(2, textures.len());
+
+    assert!(textures[0]
+        .image
+        .pixels()
+        .all(|p| p.0 == [128, 64, 255, 255]));
+
+    let gradient = &textures[1].image;
+    for y in 0..4 {
+        let row: Vec<_> = (0..4).map(|x| gradient.get_pixel(x, y).0[0]).collect();
+        assert_eq!(vec![0, 84, 171, 255], row);
+        assert_eq!(255, gradient.get_pixel(3, y).0[3]);
+    }
+}
+
+/// Builds a KTX container of a 4x4 texture of the given OpenGL internal
+/// format with a single block in every mip level.
+fn ktx(internal_format: u32, levels: &[&[u8]]) -> Vec<u8> {
+    let mut data = vec![
+        0xAB, b'K', b'T', b'X', b' ', b'1', b'1', 0xBB, b'\r', b'\n', 0x1A, b'\n',
+    ];
+    // Endianness, type, type size, format, internal format, base internal
+    // format, width, height, depth, array elements, faces, mip levels and
+    // key-value data size.
+    let header = [
+        0x0403_0201,
+        0,
+        1,
+        0,
+        internal_format,
+        0x1908,
+        4,
+        4,
+        0,
+        0,
+        1,
+        levels.len() as u32,
+        0,
+    ];
+    for value in &header {
+        data.extend_from_slice(&value.to_le_bytes());
+    }
+    for block in levels {
+        data.extend_from_slice(&(block.len() as u32).to_le_bytes());
+        data.extend_from_slice(block);
+    }
+
+    data
+}
+
+/// Builds a 4x4 texture tag embedding a KTX container.
+fn ktx_texture(ktx: &[u8]) -> Vec<u8> {
+    let mut data = vec![45];
+    data.extend_from_slice(&(9 + ktx.len() as u32).to_le_bytes());
+    data.extend_from_slice(&[0, 4, 0, 4, 0]);
+    data.extend_from_slice(&(ktx.len() as u32).to_le_bytes());
+    data.extend_from_slice(ktx);
+
+    data
+}
+
+#[test]
+fn test_ktx() {
+    let out_dir = Path::new("./tests/out/ktx");
+
+    prepare_out_dir(out_dir);
+
+    // Void-extent block with a constant color.
+    let block = [
+        0xFC, 0xFD, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00, 0x80, 0x00, 0x40, 0xFF, 0xFF, 0xFF,
+        0xFF,
+    ];
+    let ktx = ktx(0x93B0, &[&block, &block]);
+    let embedded = ktx_texture(&ktx);
+
+    fs::write(out_dir.join("external.zktx"), zstd_frame(&ktx)).unwrap();
+    let mut external = vec![47];
+    external.extend_from_slice(&(6 + 13u32).to_le_bytes());
+    external.extend_from_slice(&[0, 4, 0, 4, 0, 13]);
+    external.extend_from_slice(b"external.zktx");
+
+    let data = container(&[embedded, external.clone()].concat());
+    let textures = decode_tex(data.as_slice(), Some(out_dir), &()).unwrap();
+    assert_eq!(2, textures.len());
+
+    assert_eq!(TextureTag::Texture9, textures[0].tag);
+    assert_eq!(1, textures[0].mipmaps.len());
+    assert_eq!((2, 2), textures[0].mipmaps[0].dimensions());
+    assert_eq!(TextureTag::Texture10, textures[1].tag);
+
+    for texture in textures {
+        assert_eq!((4, 4), (texture.width, texture.height));
+        assert!(texture.image.pixels().all(|p| p.0 == [128, 64, 255, 255]));
+    }
+
+    let data = container(&external);
+    match decode_tex(data.as_slice(), None, &()) {
+        Err(Error::MissingTexture { path }) => assert_eq!(Path::new("external.zktx"), path),
+        _ => panic!("Expected a missing texture error."),
+    }
+}
+
+#[test]
+fn test_bad_ktx() {
+    let block = [0; 16];
+    for &level_count in &[33, u32::MAX] {
+        let blocks = vec![&block[..]; 33];
+        let mut ktx = ktx(0x93B0, &blocks);
+        ktx[56..60].copy_from_slice(&level_count.to_le_bytes());
+
+        let data = container(&ktx_texture(&ktx));
+        match decode_tex(data.as_slice(), None, &()) {
+            Err(Error::KtxError(_)) => (),
+            _ => panic!("Expected a KTX error."),
+        }
+    }
+}
+
+#[test]
+fn test_tags() {
+    for tag in &[1, 16, 19, 24, 27, 28, 29, 34, 45, 47] {
+        assert_eq!(*tag, TextureTag::from_tag(*tag).unwrap().tag());
+    }
+    assert_eq!(None, TextureTag::from_tag(2));
+
+    let tag = TextureTag::from_tag(29).unwrap();
+    assert!(tag.is_tiled());
+    assert_eq!(Filter::LinearMipmap, tag.filter());
+    assert_eq!(Filter::Nearest, TextureTag::Texture8.filter());
+}
+
+#[test]
+fn test_premultiplied() {
+    let mut image = RgbaImage::from_raw(
+        3,
+        1,
+        vec![100, 50, 0, 128, 255, 255, 255, 0, 10, 20, 30, 255],
+    )
+    .unwrap();
+
+    unpremultiply(&mut image);
+    assert_eq!(
+        vec![199, 100, 0, 128, 0, 0, 0, 0, 10, 20, 30, 255],
+        image.clone().into_raw()
+    );
+
+    premultiply(&mut image);
+    assert_eq!(
+        vec![100, 50, 0, 128, 0, 0, 0, 0, 10, 20, 30, 255],
+        image.into_raw()
+    );
+}
+
+#[test]
+fn test_formats() {
+    let out_dir = Path::new("./tests/out/formats");
+
+    prepare_out_dir(out_dir);
+
+    let pixels = [255, 0, 0, 255, 0, 255, 0, 128, 0, 0, 255, 0, 10, 20, 30, 40];
+    let mut data = vec![1];
+    data.extend_from_slice(&(5 + pixels.len() as u32).to_le_bytes());
+    data.extend_from_slice(&[0, 2, 0, 2, 0]);
+    data.extend_from_slice(&pixels);
+    let data = container(&data);
+
+    let formats = [
+        OutputFormat::Png,
+        OutputFormat::WebP,
+        OutputFormat::Tga,
+        OutputFormat::Bmp,
+        OutputFormat::Qoi,
+    ];
+    for format in &formats {
+        let options = ExportOptions {
+            format: *format,
+            png_compression: PngCompression::Best,
+            ..ExportOptions::default()
+        };
+        process_tex(
+            data.as_slice(),
+            "formats_tex.sc",
+            out_dir,
+            out_dir,
+            &options,
+            &(),
+        )
+        .unwrap();
+
+        let path = out_dir.join(format!("formats_tex.{}", format.extension()));
+        let image = image::open(&path).unwrap().to_rgba8();
+        assert_eq!(pixels.to_vec(), image.into_raw());
+    }
+
+    assert_eq!(Ok(OutputFormat::WebP), "WebP".parse());
+    assert!("dds".parse::<OutputFormat>().is_err());
+}
@@ -1,5 +1,6 @@
 use std::{fs::create_dir_all, path::Path};
 
+#[allow(dead_code)]
 pub fn prepare_out_dir(out_dir: &Path) {
     if !out_dir.exists() {
         create_dir_all(out_dir)
@@ -7,6 +8,7 @@ pub fn prepare_out_dir(out_dir: &Path) {
     }
 }
 
+#[allow(dead_code)]
 pub fn get_file_name(path: &Path) -> &str {
     path
         .file_name()
@@ -14,3 +16,47 @@ pub fn get_file_name(path: &Path) -> &str {
         .to_str()
         .expect("Expected file to have a valid UTF-8 name.")
 }
+
+/// Wraps `data` in an uncompressed Zstandard frame.
+#[allow(dead_code)]
+pub fn zstd_frame(data: &[u8]) -> Vec<u8> {
+    // Frame magic, single segment header with a 4-byte content size.
+    let mut out = vec![0x28, 0xb5, 0x2f, 0xfd, 0xa0];
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+
+    // A single, last, raw block.
+    let block_header = ((data.len() as u32) << 3) | 1;
+    out.extend_from_slice(&block_header.to_le_bytes()[..3]);
+    out.extend_from_slice(data);
+
+    out
+}
+
+/// Wraps `data` in a version 3 container with an uncompressed Zstandard frame.
+#[allow(dead_code)]
+pub fn container(data: &[u8]) -> Vec<u8> {
+    let mut out = b"SC\x00\x00\x00\x03\x00\x00\x00\x00".to_vec();
+    out.extend_from_slice(&zstd_frame(data));
+
+    out
+}
+
+/// Builds a `.sc` file with the given header counts and tags.
+#[allow(dead_code)]
+pub fn sc_file(counts: [u16; 6], tags: &[(u8, Vec<u8>)]) -> Vec<u8> {
+    let mut data = Vec::new();
+    for count in &counts {
+        data.extend_from_slice(&count.to_le_bytes());
+    }
+    // Unused bytes and no exports.
+    data.extend_from_slice(&[0; 7]);
+
+    for (tag, body) in tags {
+        data.push(*tag);
+        data.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        data.extend_from_slice(body);
+    }
+    data.extend_from_slice(&[0; 5]);
+
+    container(&data)
+}